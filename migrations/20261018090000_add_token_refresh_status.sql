@@ -0,0 +1,7 @@
+-- Track the outcome of the last refresh so token health can be reported
+
+ALTER TABLE tokens
+    ADD COLUMN last_refresh_attempt_at TIMESTAMPTZ,
+    ADD COLUMN last_refreshed_at TIMESTAMPTZ,
+    ADD COLUMN last_refresh_error TEXT,
+    ADD COLUMN reauthorization_required BOOLEAN NOT NULL DEFAULT FALSE;
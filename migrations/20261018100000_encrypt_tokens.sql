@@ -0,0 +1,10 @@
+-- Store token secrets sealed with envelope encryption.
+-- The plaintext columns are kept nullable until every row has been re-encrypted.
+
+ALTER TABLE tokens
+    ALTER COLUMN access_token DROP NOT NULL,
+    ALTER COLUMN refresh_token DROP NOT NULL,
+    ADD COLUMN key_id TEXT,
+    ADD COLUMN wrapped_key BYTEA,
+    ADD COLUMN access_token_sealed BYTEA,
+    ADD COLUMN refresh_token_sealed BYTEA;
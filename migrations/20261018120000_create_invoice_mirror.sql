@@ -0,0 +1,60 @@
+-- A local copy of the Zoho Books invoices of each organization, kept up to
+-- date by pulling only what changed since the last sync.
+
+CREATE TABLE customers (
+    organization_id TEXT NOT NULL,
+    customer_id TEXT NOT NULL,
+    customer_name TEXT NOT NULL,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (organization_id, customer_id)
+);
+
+CREATE TABLE invoices (
+    organization_id TEXT NOT NULL,
+    invoice_id TEXT NOT NULL,
+    invoice_number TEXT NOT NULL,
+    status TEXT NOT NULL,
+    date DATE NOT NULL,
+    customer_id TEXT NOT NULL,
+    salesperson_id TEXT,
+    salesperson_name TEXT,
+    currency_code TEXT NOT NULL,
+    total NUMERIC NOT NULL,
+    balance NUMERIC NOT NULL,
+    created_time TIMESTAMPTZ NOT NULL,
+    last_modified_time TIMESTAMPTZ,
+    -- the invoice as Zoho sent it, from which reads rebuild the full invoice
+    payload JSONB NOT NULL,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- set once the invoice has disappeared from Zoho
+    deleted_at TIMESTAMPTZ,
+    PRIMARY KEY (organization_id, invoice_id),
+    FOREIGN KEY (organization_id, customer_id) REFERENCES customers (organization_id, customer_id)
+);
+
+CREATE INDEX invoices_organization_date ON invoices (organization_id, date) WHERE deleted_at IS NULL;
+
+CREATE TABLE line_items (
+    organization_id TEXT NOT NULL,
+    invoice_id TEXT NOT NULL,
+    line_item_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    item_id TEXT,
+    name TEXT NOT NULL,
+    quantity NUMERIC NOT NULL,
+    rate NUMERIC NOT NULL,
+    purchase_rate NUMERIC,
+    item_total NUMERIC NOT NULL,
+    PRIMARY KEY (organization_id, invoice_id, line_item_id),
+    FOREIGN KEY (organization_id, invoice_id) REFERENCES invoices (organization_id, invoice_id) ON DELETE CASCADE
+);
+
+CREATE TABLE sync_cursors (
+    organization_id TEXT PRIMARY KEY,
+    -- the newest `last_modified_time` pulled so far; the next sync asks Zoho for anything since
+    last_modified_time TIMESTAMPTZ,
+    last_synced_at TIMESTAMPTZ,
+    -- when the full list of invoice IDs was last compared to find deletions
+    reconciled_at TIMESTAMPTZ,
+    last_error TEXT
+);
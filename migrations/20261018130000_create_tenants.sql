@@ -0,0 +1,30 @@
+-- Tenants are the businesses sharing one delivr deployment, each with its own
+-- Zoho connection, users and organizations.
+
+CREATE TABLE tenants (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    -- business dates of the tenant are taken in this timezone; NULL falls back
+    -- to `application.timezone`
+    timezone TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- everything that existed before tenants belongs to the `default` tenant
+INSERT INTO tenants (id, name) VALUES (gen_random_uuid(), 'default');
+
+ALTER TABLE tokens ADD COLUMN tenant_id UUID REFERENCES tenants (id) ON DELETE CASCADE;
+UPDATE tokens SET tenant_id = (SELECT id FROM tenants WHERE name = 'default');
+ALTER TABLE tokens
+    ALTER COLUMN tenant_id SET NOT NULL,
+    DROP CONSTRAINT tokens_pkey,
+    ADD PRIMARY KEY (tenant_id, scope);
+
+ALTER TABLE users ADD COLUMN tenant_id UUID REFERENCES tenants (id) ON DELETE CASCADE;
+UPDATE users SET tenant_id = (SELECT id FROM tenants WHERE name = 'default');
+ALTER TABLE users ALTER COLUMN tenant_id SET NOT NULL;
+
+-- the mirror of an organization is read by the tenant whose token synced it
+ALTER TABLE sync_cursors ADD COLUMN tenant_id UUID REFERENCES tenants (id) ON DELETE CASCADE;
+UPDATE sync_cursors SET tenant_id = (SELECT id FROM tenants WHERE name = 'default');
+ALTER TABLE sync_cursors ALTER COLUMN tenant_id SET NOT NULL;
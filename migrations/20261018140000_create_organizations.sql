@@ -0,0 +1,15 @@
+-- The Zoho organizations each tenant's token can access, as last listed by Zoho.
+CREATE TABLE organizations (
+    tenant_id UUID NOT NULL REFERENCES tenants (id) ON DELETE CASCADE,
+    organization_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    is_default_org BOOLEAN NOT NULL DEFAULT FALSE,
+    currency_code TEXT NOT NULL,
+    time_zone TEXT,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (tenant_id, organization_id)
+);
+
+-- The organization requests without an `organization_id` are for. NULL means
+-- the one Zoho opens by default, or the only one the tenant has.
+ALTER TABLE tenants ADD COLUMN default_organization_id TEXT;
@@ -0,0 +1,6 @@
+-- How each tenant pays its salespeople commission, see `CommissionPlan`.
+CREATE TABLE commission_plans (
+    tenant_id UUID PRIMARY KEY REFERENCES tenants (id) ON DELETE CASCADE,
+    plan JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+-- Sealed secrets are bound to their tenant as well as their scope. Rows sealed
+-- before keep FALSE until `delivr reencrypt-tokens` seals them again.
+ALTER TABLE tokens ADD COLUMN sealed_with_tenant BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::TokenProvider;
+use crate::config;
+use crate::error::Result;
+use crate::zoho::{Client, Invoice, InvoiceId, OwnedQuery, Query};
+
+/// Invoice lists and details fetched from Zoho, kept in memory.
+///
+/// Entries are fresh for a TTL that depends on how old their business date
+/// is: today's invoices still change, past ones rarely do. Callers pass in
+/// the tenant's today, as the tenant's timezone decides when a day is past. A stale entry is
+/// still served for a while, but triggers a refresh in the background.
+/// Identical requests arriving while a fetch is in flight wait for it instead
+/// of asking Zoho again.
+///
+/// Entries are keyed by tenant as well as organization, so a tenant is only
+/// served what its own token was allowed to fetch.
+#[derive(Clone, Debug)]
+pub struct InvoiceCache {
+    client: Client,
+    token_provider: TokenProvider,
+    today_ttl: Duration,
+    past_ttl: Duration,
+    lists: Arc<Store<(Uuid, OwnedQuery), Vec<Invoice>>>,
+    invoices: Arc<Store<(Uuid, String, InvoiceId), Invoice>>,
+}
+
+impl InvoiceCache {
+    pub fn new(client: Client, token_provider: TokenProvider, config: &config::Cache) -> Self {
+        let stale = Duration::from_secs(config.stale_secs);
+
+        Self {
+            client,
+            token_provider,
+            today_ttl: Duration::from_secs(config.today_ttl_secs),
+            past_ttl: Duration::from_secs(config.past_ttl_secs),
+            lists: Arc::new(Store::new(stale)),
+            invoices: Arc::new(Store::new(stale)),
+        }
+    }
+
+    pub async fn invoices(
+        &self,
+        tenant_id: Uuid,
+        today: NaiveDate,
+        query: &Query<'_>,
+    ) -> Result<Vec<Invoice>> {
+        let cache = self.clone();
+        let fetch =
+            move |key: (Uuid, OwnedQuery)| async move { cache.fetch_invoices(key, today).await };
+
+        self.lists
+            .get((tenant_id, OwnedQuery::from(query)), fetch)
+            .await
+    }
+
+    pub async fn invoice(
+        &self,
+        tenant_id: Uuid,
+        today: NaiveDate,
+        id: &InvoiceId,
+        query: &Query<'_>,
+    ) -> Result<Invoice> {
+        let cache = self.clone();
+        let fetch = move |key: (Uuid, String, InvoiceId)| async move {
+            cache.fetch_invoice(key, today).await
+        };
+
+        let key = (tenant_id, query.organization_id.to_string(), id.clone());
+        self.invoices.get(key, fetch).await
+    }
+
+    /// Forgets everything cached for the organization, whichever tenant fetched it.
+    pub fn invalidate_organization(&self, organization_id: &str) {
+        self.lists
+            .invalidate(|(_, query)| query.organization_id() == organization_id);
+        self.invoices
+            .invalidate(|(_, organization, _)| organization == organization_id);
+    }
+
+    /// Forgets the invoice, and every list it may have been part of.
+    pub fn invalidate_invoice(&self, organization_id: &str, id: &InvoiceId) {
+        self.lists
+            .invalidate(|(_, query)| query.organization_id() == organization_id);
+        self.invoices.invalidate(|(_, organization, invoice_id)| {
+            organization == organization_id && invoice_id == id
+        });
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_invoices(
+        &self,
+        key: (Uuid, OwnedQuery),
+        today: NaiveDate,
+    ) -> Result<(Vec<Invoice>, Duration)> {
+        let (tenant_id, key) = &key;
+        let query = key.as_query();
+        let token = self.token_provider.get(*tenant_id).await?;
+        let invoices = self.client.get_invoices(&token, &query).await?;
+
+        Ok((invoices, self.ttl(today, query.last_date())))
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_invoice(
+        &self,
+        key: (Uuid, String, InvoiceId),
+        today: NaiveDate,
+    ) -> Result<(Invoice, Duration)> {
+        let (tenant_id, organization_id, id) = &key;
+        let query = Query::builder().organization_id(organization_id).build()?;
+        let token = self.token_provider.get(*tenant_id).await?;
+        let invoice = self.client.get_invoice(&token, id, &query).await?;
+
+        let ttl = self.ttl(today, Some(invoice.date));
+        Ok((invoice, ttl))
+    }
+
+    /// Open ended queries reach today, so they get the short TTL too.
+    fn ttl(&self, today: NaiveDate, last_date: Option<NaiveDate>) -> Duration {
+        match last_date {
+            Some(date) if date < today => self.past_ttl,
+            _ => self.today_ttl,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    fetched_at: Instant,
+    ttl: Duration,
+}
+
+enum Lookup<V> {
+    Fresh(V),
+    Stale(V),
+    Missing,
+}
+
+/// The entries of one kind of value, with a lock per key so that only one
+/// fetch per key is ever in flight.
+#[derive(Debug)]
+struct Store<K, V> {
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    in_flight: Mutex<HashMap<K, Arc<tokio::sync::Mutex<()>>>>,
+    /// Bumped by every invalidation, so fetches started before one do not
+    /// put their now outdated result back.
+    generation: AtomicU64,
+    stale: Duration,
+}
+
+impl<K, V> Store<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync + std::fmt::Debug + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn new(stale: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+            stale,
+        }
+    }
+
+    /// The cached value for `key`, fetching it when there is none yet. `fetch`
+    /// returns the value along with how long it stays fresh.
+    async fn get<F, Fut>(self: &Arc<Self>, key: K, fetch: F) -> Result<V>
+    where
+        F: FnOnce(K) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(V, Duration)>> + Send + 'static,
+    {
+        match self.lookup(&key) {
+            Lookup::Fresh(value) => return Ok(value),
+            Lookup::Stale(value) => {
+                self.revalidate(key, fetch);
+                return Ok(value);
+            }
+            Lookup::Missing => {}
+        }
+
+        let lock = self.lock_for(&key);
+        let _guard = lock.lock().await;
+
+        // another task may have fetched while we were waiting
+        if let Lookup::Fresh(value) | Lookup::Stale(value) = self.lookup(&key) {
+            return Ok(value);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let (value, ttl) = fetch(key.clone()).await?;
+        self.insert(key, value.clone(), ttl, generation);
+
+        Ok(value)
+    }
+
+    fn lookup(&self, key: &K) -> Lookup<V> {
+        let entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get(key) else {
+            return Lookup::Missing;
+        };
+
+        let age = entry.fetched_at.elapsed();
+        if age < entry.ttl {
+            Lookup::Fresh(entry.value.clone())
+        } else if age < entry.ttl + self.stale {
+            Lookup::Stale(entry.value.clone())
+        } else {
+            Lookup::Missing
+        }
+    }
+
+    /// Refreshes the entry in the background, unless that is already happening.
+    fn revalidate<F, Fut>(self: &Arc<Self>, key: K, fetch: F)
+    where
+        F: FnOnce(K) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(V, Duration)>> + Send + 'static,
+    {
+        let Ok(guard) = self.lock_for(&key).try_lock_owned() else {
+            return;
+        };
+
+        let store = self.clone();
+        let generation = self.generation.load(Ordering::SeqCst);
+        tokio::spawn(async move {
+            let _guard = guard;
+
+            match fetch(key.clone()).await {
+                Ok((value, ttl)) => store.insert(key, value, ttl, generation),
+                Err(err) => tracing::warn!(?key, "cache refresh failed: {err:?}"),
+            }
+        });
+    }
+
+    fn insert(&self, key: K, value: V, ttl: Duration, generation: u64) {
+        // locks only the map still refers to are neither held nor waited for
+        self.in_flight
+            .lock()
+            .unwrap()
+            .retain(|_, lock| Arc::strong_count(lock) > 1);
+
+        let mut entries = self.entries.lock().unwrap();
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+
+        // drop what can no longer be served, so the map does not grow forever
+        entries.retain(|_, entry| entry.fetched_at.elapsed() < entry.ttl + self.stale);
+        entries.insert(
+            key,
+            Entry {
+                value,
+                fetched_at: Instant::now(),
+                ttl,
+            },
+        );
+    }
+
+    fn invalidate(&self, matches: impl Fn(&K) -> bool) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        entries.retain(|key, _| !matches(key));
+    }
+
+    fn lock_for(&self, key: &K) -> Arc<tokio::sync::Mutex<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight.entry(key.clone()).or_default().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    fn counting_fetch(
+        calls: &Arc<AtomicUsize>,
+        ttl: Duration,
+    ) -> impl FnOnce(&'static str) -> std::future::Ready<Result<(usize, Duration)>> {
+        let calls = calls.clone();
+        move |_| {
+            let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            std::future::ready(Ok((n, ttl)))
+        }
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_served_without_fetching() -> Result<()> {
+        let store = Arc::new(Store::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(store.get("a", counting_fetch(&calls, ttl)).await?, 1);
+        assert_eq!(store.get("a", counting_fetch(&calls, ttl)).await?, 1);
+        assert_eq!(store.get("b", counting_fetch(&calls, ttl)).await?, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn idle_locks_are_dropped() -> Result<()> {
+        let store = Arc::new(Store::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ttl = Duration::from_secs(60);
+
+        store.get("a", counting_fetch(&calls, ttl)).await?;
+        store.get("b", counting_fetch(&calls, ttl)).await?;
+
+        // only the lock of the latest fetch is left
+        let in_flight = store.in_flight.lock().unwrap();
+        assert_eq!(in_flight.keys().collect::<Vec<_>>(), [&"b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_served_while_refreshing() -> Result<()> {
+        let store = Arc::new(Store::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        store
+            .get("a", counting_fetch(&calls, Duration::ZERO))
+            .await?;
+        let stale = store
+            .get("a", counting_fetch(&calls, Duration::from_secs(60)))
+            .await?;
+        assert_eq!(stale, 1);
+
+        // the background refresh holds the key's lock until it is done
+        drop(store.lock_for(&"a").lock().await);
+        assert!(matches!(store.lookup(&"a"), Lookup::Fresh(2)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn identical_requests_share_one_fetch() -> Result<()> {
+        let store = Arc::new(Store::new(Duration::ZERO));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let slow = |calls: Arc<AtomicUsize>| {
+            move |_| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok((
+                    calls.fetch_add(1, Ordering::SeqCst) + 1,
+                    Duration::from_secs(60),
+                ))
+            }
+        };
+        let (a, b) = tokio::join!(
+            store.get("a", slow(calls.clone())),
+            store.get("a", slow(calls.clone()))
+        );
+
+        assert_eq!((a?, b?), (1, 1));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalidated_entries_are_fetched_again() -> Result<()> {
+        let store = Arc::new(Store::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ttl = Duration::from_secs(60);
+
+        store.get("a", counting_fetch(&calls, ttl)).await?;
+        store.get("b", counting_fetch(&calls, ttl)).await?;
+        store.invalidate(|key| *key == "a");
+
+        assert_eq!(store.get("a", counting_fetch(&calls, ttl)).await?, 3);
+        assert_eq!(store.get("b", counting_fetch(&calls, ttl)).await?, 2);
+        Ok(())
+    }
+}
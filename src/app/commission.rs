@@ -0,0 +1,289 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+
+use crate::app::reports::by_salesperson;
+use crate::error::{Error, Result};
+use crate::zoho::{CustomerId, Invoice, InvoiceId, Money, SalespersonId, Summary};
+
+/// What a commission is a percentage of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Basis {
+    /// Net sales, after discounts and exclusive of tax.
+    Sales,
+    /// Profit, as in [`Invoice::profit`].
+    Profit,
+}
+
+/// A rate that applies once a salesperson's basis amount reaches `target`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Tier {
+    pub target: Money,
+    /// A percentage, such as `5` for 5%.
+    pub rate: BigDecimal,
+}
+
+/// How a tenant pays commission.
+///
+/// The rate of the highest tier a salesperson reached applies to their whole
+/// basis amount for the pay period; below the first target there is no
+/// commission. A flat percentage is a single tier with a target of zero.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct CommissionPlan {
+    pub basis: Basis,
+    pub tiers: Vec<Tier>,
+}
+
+impl CommissionPlan {
+    pub fn flat(basis: Basis, rate: BigDecimal) -> Self {
+        Self {
+            basis,
+            tiers: vec![Tier {
+                target: Money::zero(),
+                rate,
+            }],
+        }
+    }
+
+    /// Tiers must exist, go up in target, and have rates from 0 to 100.
+    pub fn validate(&self) -> Result<()> {
+        if self.tiers.is_empty() {
+            return Err(Error::BadRequest(
+                "A commission plan needs at least one tier".to_string(),
+            ));
+        }
+        if self
+            .tiers
+            .windows(2)
+            .any(|tiers| tiers[0].target >= tiers[1].target)
+        {
+            return Err(Error::BadRequest(
+                "Commission tiers must be in increasing order of target".to_string(),
+            ));
+        }
+        let hundred = BigDecimal::from(100);
+        if self
+            .tiers
+            .iter()
+            .any(|tier| tier.rate < BigDecimal::zero() || tier.rate > hundred)
+        {
+            return Err(Error::BadRequest(
+                "Commission rates are percentages from 0 to 100".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The highest tier `amount` reaches.
+    pub fn tier_for(&self, amount: &Money) -> Option<&Tier> {
+        self.tiers.iter().rev().find(|tier| &tier.target <= amount)
+    }
+}
+
+/// The commission of every salesperson over a pay period, with the invoices
+/// it was earned on. Invoices without a salesperson earn no commission.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CommissionReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub plan: CommissionPlan,
+    pub statements: Vec<CommissionStatement>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CommissionStatement {
+    pub salesperson_id: SalespersonId,
+    pub salesperson_name: Option<String>,
+    /// Net sales or profit, whichever the plan is based on. `None` when based
+    /// on profit and the profit of an invoice is unknown.
+    pub basis_amount: Option<Money>,
+    /// The tier reached, `None` below the first target.
+    pub tier: Option<Tier>,
+    /// Never negative, so a loss does not take commission back.
+    pub commission: Option<Money>,
+    pub invoices: Vec<CommissionLine>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CommissionLine {
+    pub invoice_id: InvoiceId,
+    pub invoice_number: String,
+    pub date: NaiveDate,
+    pub customer_id: CustomerId,
+    pub customer_name: String,
+    pub net_sales: Money,
+    pub profit: Option<Money>,
+}
+
+impl CommissionReport {
+    /// `invoices` are those of the pay period, those that are no sales are left out.
+    pub fn of(invoices: &[Invoice], from: NaiveDate, to: NaiveDate, plan: CommissionPlan) -> Self {
+        let statements = by_salesperson(invoices, from, to)
+            .into_iter()
+            .filter_map(|(salesperson_id, invoices)| {
+                let salesperson_id = salesperson_id?;
+                Some(CommissionStatement::of(salesperson_id, &invoices, &plan))
+            })
+            .collect();
+
+        Self {
+            from,
+            to,
+            plan,
+            statements,
+        }
+    }
+}
+
+impl CommissionStatement {
+    fn of(salesperson_id: SalespersonId, invoices: &[&Invoice], plan: &CommissionPlan) -> Self {
+        let summary = Summary::of(invoices.iter().copied());
+        let salesperson_name = invoices
+            .iter()
+            .find_map(|invoice| invoice.salesperson_name.clone());
+        let basis_amount = match plan.basis {
+            Basis::Sales => Some(summary.net_sales),
+            Basis::Profit => summary.profit,
+        };
+        let tier = basis_amount
+            .as_ref()
+            .and_then(|amount| plan.tier_for(amount))
+            .cloned();
+        let commission = basis_amount.as_ref().map(|amount| match &tier {
+            Some(tier) if amount > &Money::zero() => {
+                Money::new(amount.amount() * &tier.rate / BigDecimal::from(100))
+            }
+            _ => Money::zero(),
+        });
+
+        let lines = invoices
+            .iter()
+            .map(|invoice| {
+                let breakdown = invoice.breakdown();
+                CommissionLine {
+                    invoice_id: invoice.invoice_id.clone(),
+                    invoice_number: invoice.invoice_number.clone(),
+                    date: invoice.date,
+                    customer_id: invoice.customer_id.clone(),
+                    customer_name: invoice.customer_name.clone(),
+                    net_sales: &breakdown.revenue - &breakdown.discount,
+                    profit: breakdown.profit,
+                }
+            })
+            .collect();
+
+        Self {
+            salesperson_id,
+            salesperson_name,
+            basis_amount,
+            tier,
+            commission,
+            invoices: lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zoho::InvoiceBuilder;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    fn tiered() -> CommissionPlan {
+        CommissionPlan {
+            basis: Basis::Sales,
+            tiers: vec![
+                Tier {
+                    target: money("150"),
+                    rate: BigDecimal::from(5),
+                },
+                Tier {
+                    target: money("300"),
+                    rate: BigDecimal::from(10),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn the_tier_reached_applies_to_all_sales() -> Result<()> {
+        let invoices = [
+            ("1", "1"),
+            ("2", "2"),
+            ("3", "2"),
+            ("4", "3"),
+            ("5", "3"),
+            ("6", "3"),
+        ]
+        .into_iter()
+        .map(|(id, salesperson_id)| {
+            InvoiceBuilder::new(id)
+                .salesperson(salesperson_id)
+                .purchase_rate(6.0)
+                .build()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        let report =
+            CommissionReport::of(&invoices, date("2024-05-01"), date("2024-05-31"), tiered());
+        let commissions: Vec<_> = report
+            .statements
+            .iter()
+            .map(|statement| statement.commission.clone())
+            .collect();
+
+        assert_eq!(
+            commissions,
+            [Some(money("0")), Some(money("10")), Some(money("30"))]
+        );
+        assert_eq!(report.statements[0].tier, None);
+        assert_eq!(report.statements[2].invoices.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn flat_profit_commission_is_never_negative() -> Result<()> {
+        let plan = CommissionPlan::flat(Basis::Profit, BigDecimal::from(10));
+        let invoices = [
+            InvoiceBuilder::new("1")
+                .salesperson("1")
+                .purchase_rate(6.0)
+                .build()?,
+            InvoiceBuilder::new("2")
+                .salesperson("2")
+                .purchase_rate(13.0)
+                .build()?,
+        ];
+
+        let report = CommissionReport::of(&invoices, date("2024-05-01"), date("2024-05-31"), plan);
+
+        assert_eq!(report.statements[0].basis_amount, Some(money("40")));
+        assert_eq!(report.statements[0].commission, Some(money("4")));
+        assert_eq!(report.statements[1].basis_amount, Some(money("-30")));
+        assert_eq!(report.statements[1].commission, Some(money("0")));
+        Ok(())
+    }
+
+    #[test]
+    fn plans_are_validated() {
+        let mut backwards = tiered();
+        backwards.tiers.reverse();
+        let too_high = CommissionPlan::flat(Basis::Sales, BigDecimal::from(101));
+        let empty = CommissionPlan {
+            basis: Basis::Sales,
+            tiers: vec![],
+        };
+
+        assert!(tiered().validate().is_ok());
+        assert!(backwards.validate().is_err());
+        assert!(too_high.validate().is_err());
+        assert!(empty.validate().is_err());
+    }
+}
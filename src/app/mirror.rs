@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use futures::TryStreamExt;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::{InvoiceCache, TokenProvider};
+use crate::config;
+use crate::database::{Invoices, SyncCursors, Tenants, DEFAULT_TENANT};
+use crate::error::{Error, Result};
+use crate::zoho::{Client, Invoice, InvoiceId, Query, SortColumn, SortOrder};
+
+/// A local copy of the invoices of each organization.
+///
+/// Once an organization has been synced, reads are answered from Postgres and
+/// Zoho is only asked for what changed since the newest `last_modified_time`
+/// seen. Voided invoices come along as changes; deleted ones never show up in
+/// a listing again, so every `reconcile_interval` the full list of invoice IDs
+/// is compared with the local copy. Only organizations the background sync
+/// keeps up to date, and whose last sync is within `freshness`, are read
+/// locally; the others are read through the cache.
+///
+/// The mirror of an organization is only read by the tenant whose token synced
+/// it; other tenants go through the cache, where Zoho checks their access.
+///
+/// Syncs of one organization take turns, whether they come from the
+/// background or from `POST /sync`: in process through a mutex per
+/// organization, across delivr instances through a Postgres advisory lock.
+#[derive(Clone, Debug)]
+pub struct Mirror {
+    pool: PgPool,
+    client: Client,
+    cache: InvoiceCache,
+    token_provider: TokenProvider,
+    /// The organizations synced in the background.
+    organization_ids: Vec<String>,
+    freshness: chrono::Duration,
+    reconcile_interval: chrono::Duration,
+    in_flight: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+/// What a sync changed.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SyncReport {
+    pub organization_id: String,
+    pub upserted: usize,
+    pub deleted: u64,
+    /// Whether Zoho was asked for everything rather than just the changes.
+    pub full: bool,
+}
+
+impl Mirror {
+    pub fn new(
+        pool: PgPool,
+        client: Client,
+        cache: InvoiceCache,
+        token_provider: TokenProvider,
+        config: &config::Mirror,
+    ) -> Self {
+        Self {
+            pool,
+            client,
+            cache,
+            token_provider,
+            organization_ids: config.organization_ids.clone(),
+            freshness: chrono::Duration::seconds(config.freshness_secs),
+            reconcile_interval: chrono::Duration::seconds(config.reconcile_interval_secs),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether the tenant's reads for the organization can be served locally.
+    ///
+    /// A manual sync alone is not enough: without the background sync the
+    /// mirror would silently fall behind Zoho.
+    pub async fn is_synced(&self, tenant_id: Uuid, organization_id: &str) -> Result<bool> {
+        if !self.organization_ids.iter().any(|id| id == organization_id) {
+            return Ok(false);
+        }
+
+        let cursors = SyncCursors { pool: &self.pool };
+        let cursor = cursors.get(organization_id).await?;
+        let fresh_since = Utc::now() - self.freshness;
+
+        Ok(cursor.is_some_and(|cursor| {
+            cursor.tenant_id == tenant_id
+                && cursor
+                    .last_synced_at
+                    .is_some_and(|synced_at| synced_at >= fresh_since)
+        }))
+    }
+
+    /// The invoices matching the query, from the mirror while the organization
+    /// is synced and from Zoho otherwise. `today` is the tenant's business date.
+    pub async fn invoices(
+        &self,
+        tenant_id: Uuid,
+        today: NaiveDate,
+        query: &Query<'_>,
+    ) -> Result<Vec<Invoice>> {
+        if self.is_synced(tenant_id, query.organization_id).await? {
+            let invoices = Invoices { pool: &self.pool };
+            return invoices.find(query).await;
+        }
+
+        self.cache.invoices(tenant_id, today, query).await
+    }
+
+    pub async fn invoice(
+        &self,
+        tenant_id: Uuid,
+        today: NaiveDate,
+        id: &InvoiceId,
+        query: &Query<'_>,
+    ) -> Result<Invoice> {
+        if self.is_synced(tenant_id, query.organization_id).await? {
+            let invoices = Invoices { pool: &self.pool };
+            if let Some(invoice) = invoices.get(query.organization_id, id).await? {
+                return Ok(invoice);
+            }
+        }
+
+        self.cache.invoice(tenant_id, today, id, query).await
+    }
+
+    /// Pulls what changed in Zoho since the last sync with the tenant's token,
+    /// and records the outcome on the organization's cursor.
+    #[instrument(skip(self))]
+    pub async fn sync(&self, tenant_id: Uuid, organization_id: &str) -> Result<SyncReport> {
+        let lock = self.lock_for(organization_id);
+        let _guard = lock.lock().await;
+
+        let result = self
+            .sync_with_advisory_lock(tenant_id, organization_id)
+            .await;
+
+        if let Err(err) = &result {
+            let cursors = SyncCursors { pool: &self.pool };
+            // the sync error is the one worth reporting
+            if let Err(record_err) = cursors
+                .record_failure(tenant_id, organization_id, &err.to_string())
+                .await
+            {
+                tracing::error!("Failed to record the sync failure: {record_err:?}");
+            }
+        }
+
+        if let Ok(report) = &result {
+            if report.upserted > 0 || report.deleted > 0 {
+                self.cache.invalidate_organization(organization_id);
+            }
+        }
+
+        result
+    }
+
+    fn lock_for(&self, organization_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight
+            .entry(organization_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The lock belongs to a transaction, so it is released however the sync
+    /// ends.
+    async fn sync_with_advisory_lock(
+        &self,
+        tenant_id: Uuid,
+        organization_id: &str,
+    ) -> Result<SyncReport> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("sync:{organization_id}"))
+            .execute(&mut *tx)
+            .await?;
+
+        let result = self.sync_changes(tenant_id, organization_id).await;
+
+        if let Err(err) = tx.commit().await {
+            tracing::warn!("Failed to release the sync lock: {err}");
+        }
+
+        result
+    }
+
+    async fn sync_changes(&self, tenant_id: Uuid, organization_id: &str) -> Result<SyncReport> {
+        tracing::info!("-->");
+
+        let cursors = SyncCursors { pool: &self.pool };
+        let invoices = Invoices { pool: &self.pool };
+        let cursor = cursors.get(organization_id).await?;
+        let since = cursor.as_ref().and_then(|cursor| cursor.last_modified_time);
+
+        let token = self.token_provider.get(tenant_id).await?;
+
+        let mut builder = Query::builder()
+            .organization_id(organization_id)
+            .sort(SortColumn::LastModifiedTime, SortOrder::Ascending);
+        if let Some(since) = since {
+            builder = builder.last_modified_time(since);
+        }
+        let query = builder.build()?;
+
+        let changed = self.client.get_invoices_with_query(&token, &query).await?;
+        let ids: Vec<InvoiceId> = changed.iter().map(|s| s.invoice_id.clone()).collect();
+
+        let detail_query = query.organization();
+        let fetched = self
+            .client
+            .get_invoices_with_payloads(&token, &ids, &detail_query)
+            .await?;
+        for (invoice, payload) in &fetched {
+            invoices.upsert(organization_id, invoice, payload).await?;
+        }
+
+        let newest = changed
+            .iter()
+            .filter_map(|summary| summary.last_modified_time)
+            .max()
+            .max(since);
+
+        // a full listing names every invoice there is, anything else is gone
+        let full = since.is_none();
+        let reconcile_due = cursor
+            .and_then(|cursor| cursor.reconciled_at)
+            .is_none_or(|at| at + self.reconcile_interval <= Utc::now());
+        let deleted = if full {
+            invoices.mark_missing_deleted(organization_id, &ids).await?
+        } else if reconcile_due {
+            let all = Query::builder().organization_id(organization_id).build()?;
+            let present: Vec<InvoiceId> = self
+                .client
+                .invoice_summaries(&token, &all)
+                .map_ok(|summary| summary.invoice_id)
+                .try_collect()
+                .await?;
+            invoices
+                .mark_missing_deleted(organization_id, &present)
+                .await?
+        } else {
+            0
+        };
+
+        cursors
+            .record_success(tenant_id, organization_id, newest, full || reconcile_due)
+            .await?;
+
+        tracing::info!("<-- {} upserted, {deleted} deleted", fetched.len());
+        Ok(SyncReport {
+            organization_id: organization_id.to_string(),
+            upserted: fetched.len(),
+            deleted,
+            full,
+        })
+    }
+}
+
+/// Syncs the configured organizations every `every`, so reads rarely find
+/// the mirror more than one interval behind Zoho.
+pub fn spawn(mirror: Mirror, organization_ids: Vec<String>, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            for organization_id in &organization_ids {
+                if let Err(err) = sync_organization(&mirror, organization_id).await {
+                    tracing::error!(organization = %organization_id, "invoice sync: {err:?}");
+                }
+            }
+        }
+    })
+}
+
+/// Syncs with the token of the tenant that synced the organization before,
+/// or of the default tenant the first time.
+async fn sync_organization(mirror: &Mirror, organization_id: &str) -> Result<()> {
+    let cursors = SyncCursors { pool: &mirror.pool };
+    let tenant_id = match cursors.get(organization_id).await? {
+        Some(cursor) => cursor.tenant_id,
+        None => {
+            let tenants = Tenants { pool: &mirror.pool };
+            tenants
+                .get_by_name(DEFAULT_TENANT)
+                .await?
+                .ok_or(Error::custom("The default tenant is missing"))?
+                .id
+        }
+    };
+
+    mirror.sync(tenant_id, organization_id).await?;
+    Ok(())
+}
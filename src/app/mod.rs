@@ -1,25 +1,113 @@
+mod cache;
+pub use cache::InvoiceCache;
+
+mod commission;
+pub use commission::{
+    Basis, CommissionLine, CommissionPlan, CommissionReport, CommissionStatement, Tier,
+};
+
+mod mirror;
+pub use mirror::{Mirror, SyncReport};
+
+mod organizations;
+pub use organizations::OrganizationDirectory;
+
+mod refresher;
+
+mod reports;
+pub use reports::{
+    Bucket, BucketSummary, CustomerSales, SalesReport, SalespersonReport, SalespersonSummary,
+    MAX_REPORT_DAYS, TOP_CUSTOMERS,
+};
+
+mod token_provider;
+pub use token_provider::TokenProvider;
+
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use secrecy::Secret;
 use sqlx::PgPool;
 use tokio::net::TcpListener;
+use uuid::Uuid;
 
+use crate::auth::{hash_password, Role, User};
 use crate::config::{Config, Environment};
-use crate::database::Database;
-use crate::error::Result;
+use crate::database::{Cipher, Database, Tenant, Tenants, Tokens, Users, DEFAULT_TENANT};
+use crate::error::{Error, Result};
 use crate::routes::build_router;
 use crate::zoho::Client;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
+    pub config: Config,
     pub pool: PgPool,
+    pub cipher: Cipher,
     pub client: Client,
+    pub token_provider: TokenProvider,
+    pub cache: InvoiceCache,
+    pub mirror: Mirror,
+    pub organizations: OrganizationDirectory,
 }
 
 impl AppState {
     pub async fn build_state(config: &Config) -> Result<AppState> {
+        let pool = PgPool::connect(&config.database.connection_string()).await?;
+        let cipher = Cipher::from_config(&config.encryption)?;
+        let client = Client::new(config);
+        let token_provider = TokenProvider::new(
+            pool.clone(),
+            cipher.clone(),
+            client.clone(),
+            config.zoho.scope.clone(),
+            chrono::Duration::seconds(config.zoho.refresh_margin_secs),
+        );
+
+        let cache = InvoiceCache::new(client.clone(), token_provider.clone(), &config.cache);
+        let mirror = Mirror::new(
+            pool.clone(),
+            client.clone(),
+            cache.clone(),
+            token_provider.clone(),
+            &config.mirror,
+        );
+
+        let organizations = OrganizationDirectory::new(
+            pool.clone(),
+            client.clone(),
+            token_provider.clone(),
+            &config.organizations,
+        );
+
         Ok(AppState {
-            pool: PgPool::connect(&config.database.connection_string()).await?,
-            client: Client::new(config),
+            config: config.clone(),
+            pool,
+            cipher,
+            client,
+            token_provider,
+            cache,
+            mirror,
+            organizations,
         })
     }
+
+    pub async fn tenant(&self, tenant_id: Uuid) -> Result<Tenant> {
+        let tenants = Tenants { pool: &self.pool };
+        tenants
+            .get_by_id(tenant_id)
+            .await?
+            .ok_or(Error::NotFound(format!(
+                "Tenant {tenant_id} does not exist"
+            )))
+    }
+
+    /// Today's business date of the tenant, in its own timezone if it set one.
+    pub async fn today(&self, tenant_id: Uuid) -> Result<NaiveDate> {
+        let tenant = self.tenant(tenant_id).await?;
+        let timezone = tenant.timezone.unwrap_or(self.config.application.timezone);
+
+        Ok(chrono::Utc::now().with_timezone(&timezone).date_naive())
+    }
 }
 
 pub async fn serve(config: &Config) -> Result<u16> {
@@ -27,7 +115,20 @@ pub async fn serve(config: &Config) -> Result<u16> {
     // run migrations
     Database::migrate(&pool).await?;
 
-    let router = build_router(config).await?;
+    let state = AppState::build_state(config).await?;
+    refresher::spawn(
+        state.token_provider.clone(),
+        Duration::from_secs(config.zoho.refresh_interval_secs),
+    );
+    if !config.mirror.organization_ids.is_empty() {
+        mirror::spawn(
+            state.mirror.clone(),
+            config.mirror.organization_ids.clone(),
+            Duration::from_secs(config.mirror.interval_secs),
+        );
+    }
+
+    let router = build_router(state);
     let listener = TcpListener::bind(config.addr()).await?;
     let port = listener.local_addr().unwrap().port();
 
@@ -46,3 +147,51 @@ pub async fn serve(config: &Config) -> Result<u16> {
 
     Ok(port)
 }
+
+/// Brings every stored token under the active encryption key.
+pub async fn reencrypt_tokens(config: &Config) -> Result<usize> {
+    let pool = PgPool::connect(&config.database.connection_string()).await?;
+    Database::migrate(&pool).await?;
+
+    let cipher = Cipher::from_config(&config.encryption)?;
+    let tokens = Tokens {
+        pool: &pool,
+        cipher: &cipher,
+    };
+
+    tokens.reencrypt_all().await
+}
+
+/// Creates a tenant from the command line; its first admin is created with `create_user`.
+pub async fn create_tenant(config: &Config, name: &str) -> Result<Tenant> {
+    let pool = PgPool::connect(&config.database.connection_string()).await?;
+    Database::migrate(&pool).await?;
+
+    let tenants = Tenants { pool: &pool };
+    tenants.insert(name).await
+}
+
+/// Creates a user from the command line, which is how the first admin of a
+/// tenant gets in. Without a tenant the user joins the `default` one.
+pub async fn create_user(
+    config: &Config,
+    tenant: Option<&str>,
+    email: &str,
+    password: Secret<String>,
+    role: Role,
+) -> Result<User> {
+    let pool = PgPool::connect(&config.database.connection_string()).await?;
+    Database::migrate(&pool).await?;
+
+    let name = tenant.unwrap_or(DEFAULT_TENANT);
+    let tenants = Tenants { pool: &pool };
+    let tenant = tenants
+        .get_by_name(name)
+        .await?
+        .ok_or(Error::NotFound(format!("Tenant {name} does not exist")))?;
+
+    let password_hash = hash_password(password).await?;
+    let users = Users { pool: &pool };
+
+    users.insert(tenant.id, email, &password_hash, role).await
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::TokenProvider;
+use crate::config;
+use crate::database::{Organizations, Tenants};
+use crate::error::{Error, Result};
+use crate::zoho::{Client, Organization};
+
+/// When a tenant was last listed, locked while it is being listed.
+type LastRefresh = Arc<tokio::sync::Mutex<Option<Instant>>>;
+
+/// Which Zoho organizations belong to which tenant.
+///
+/// The list is taken from Zoho with the tenant's own token and stored for
+/// `ttl`, so the check that guards every organization scoped request is a
+/// local lookup. An organization that is not known yet triggers one more
+/// listing, in case it was created since, but at most one per tenant every
+/// `refresh_interval` so made up IDs cannot use up the Zoho rate limit.
+/// Listings of one tenant take turns, and callers waiting for one use its
+/// result instead of listing again.
+#[derive(Clone, Debug)]
+pub struct OrganizationDirectory {
+    pool: PgPool,
+    client: Client,
+    token_provider: TokenProvider,
+    ttl: chrono::Duration,
+    refresh_interval: Duration,
+    last_refresh: Arc<Mutex<HashMap<Uuid, LastRefresh>>>,
+}
+
+impl OrganizationDirectory {
+    pub fn new(
+        pool: PgPool,
+        client: Client,
+        token_provider: TokenProvider,
+        config: &config::Organizations,
+    ) -> Self {
+        Self {
+            pool,
+            client,
+            token_provider,
+            ttl: chrono::Duration::seconds(config.ttl_secs),
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs),
+            last_refresh: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The tenant's organizations, listed from Zoho again once the stored
+    /// list is older than `ttl`.
+    pub async fn list(&self, tenant_id: Uuid) -> Result<Vec<Organization>> {
+        let organizations = Organizations { pool: &self.pool };
+        let stored = organizations.get_all(tenant_id).await?;
+        let synced_at = organizations.synced_at(tenant_id).await?;
+        if synced_at.is_some_and(|synced_at| synced_at + self.ttl > Utc::now()) {
+            return Ok(stored);
+        }
+
+        match self.refresh_if_due(tenant_id).await {
+            Ok(Some(listed)) => Ok(listed),
+            // listed a moment ago, maybe while we were waiting
+            Ok(None) => organizations.get_all(tenant_id).await,
+            Err(err) if !stored.is_empty() => {
+                tracing::warn!("Serving the stored organizations, listing failed: {err:?}");
+                Ok(stored)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lists the tenant's organizations from Zoho and stores them.
+    pub async fn refresh(&self, tenant_id: Uuid) -> Result<Vec<Organization>> {
+        let lock = self.lock_for(tenant_id);
+        let mut last_refresh = lock.lock().await;
+
+        *last_refresh = Some(Instant::now());
+        self.list_from_zoho(tenant_id).await
+    }
+
+    /// The organization to use when a request names none: the one the tenant
+    /// configured, or else the one discovered from its organizations.
+    pub async fn default_for(&self, tenant_id: Uuid) -> Result<Option<String>> {
+        let tenants = Tenants { pool: &self.pool };
+        let configured = tenants
+            .get_by_id(tenant_id)
+            .await?
+            .and_then(|tenant| tenant.default_organization_id);
+        if configured.is_some() {
+            return Ok(configured);
+        }
+
+        let organizations = self.list(tenant_id).await?;
+        Ok(Organization::default_of(&organizations)
+            .map(|organization| organization.organization_id.clone()))
+    }
+
+    /// Fails unless the organization belongs to the tenant.
+    pub async fn authorize(&self, tenant_id: Uuid, organization_id: &str) -> Result<()> {
+        let organizations = Organizations { pool: &self.pool };
+        if organizations.contains(tenant_id, organization_id).await? {
+            return Ok(());
+        }
+
+        let known = match self.refresh_if_due(tenant_id).await? {
+            Some(listed) => listed
+                .iter()
+                .any(|organization| organization.organization_id == organization_id),
+            // listed a moment ago, maybe while we were waiting
+            None => organizations.contains(tenant_id, organization_id).await?,
+        };
+        if known {
+            return Ok(());
+        }
+
+        tracing::warn!(tenant = %tenant_id, organization_id, "organization of another tenant");
+        Err(Error::UnknownOrganization(organization_id.to_string()))
+    }
+
+    /// Lists the tenant again, unless that happened within `refresh_interval`.
+    async fn refresh_if_due(&self, tenant_id: Uuid) -> Result<Option<Vec<Organization>>> {
+        let lock = self.lock_for(tenant_id);
+        let mut last_refresh = lock.lock().await;
+        if last_refresh.is_some_and(|last| last.elapsed() < self.refresh_interval) {
+            return Ok(None);
+        }
+
+        *last_refresh = Some(Instant::now());
+        self.list_from_zoho(tenant_id).await.map(Some)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_from_zoho(&self, tenant_id: Uuid) -> Result<Vec<Organization>> {
+        let token = self.token_provider.get(tenant_id).await?;
+        let listed = self.client.get_organizations(&token).await?;
+
+        let organizations = Organizations { pool: &self.pool };
+        organizations.replace_all(tenant_id, &listed).await?;
+
+        tracing::info!("{} organizations", listed.len());
+        Ok(listed)
+    }
+
+    fn lock_for(&self, tenant_id: Uuid) -> LastRefresh {
+        let mut last_refresh = self.last_refresh.lock().unwrap();
+        last_refresh.entry(tenant_id).or_default().clone()
+    }
+}
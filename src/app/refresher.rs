@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::app::TokenProvider;
+use crate::database::TokenStatus;
+use crate::error::{Error, Result};
+
+/// Keeps every stored token fresh so user requests never wait on a refresh,
+/// and surfaces tokens whose grant has been revoked.
+pub fn spawn(provider: TokenProvider, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = refresh_all(&provider).await {
+                tracing::error!("token refresher: {err:?}");
+            }
+        }
+    })
+}
+
+async fn refresh_all(provider: &TokenProvider) -> Result<()> {
+    for health in provider.tokens().get_health_all().await? {
+        if health.status == TokenStatus::ReauthorizationRequired {
+            tracing::warn!(
+                tenant = %health.tenant_id,
+                scope = %health.scope,
+                "Zoho token must be re-authorised: {}",
+                health.last_refresh_error.as_deref().unwrap_or("unknown error")
+            );
+            continue;
+        }
+
+        match provider
+            .get_for_scope(health.tenant_id, &health.scope)
+            .await
+        {
+            Ok(_) => {}
+            Err(Error::Zoho(err)) if err.requires_reauthorization() => {
+                tracing::warn!(
+                    tenant = %health.tenant_id,
+                    scope = %health.scope,
+                    "Zoho token must be re-authorised: {err}"
+                );
+            }
+            Err(err) => {
+                tracing::error!(
+                    tenant = %health.tenant_id,
+                    scope = %health.scope,
+                    "Failed to refresh token: {err:?}"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+use crate::zoho::{CustomerId, Invoice, Money, SalespersonId, Summary};
+
+/// How many of a salesperson's best customers are listed.
+pub const TOP_CUSTOMERS: usize = 5;
+
+/// How many days a report may cover, so one request cannot pull years of
+/// invoices from Zoho.
+pub const MAX_REPORT_DAYS: i64 = 366;
+
+/// The period the invoices of a report are grouped by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    #[default]
+    Day,
+    /// Monday to Sunday.
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// The first day of the bucket `date` falls in.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => date,
+            Bucket::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Bucket::Month => date.with_day(1).expect("every month has a first day"),
+        }
+    }
+
+    /// The first day of the bucket after the one starting at `start`.
+    fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => start + Days::new(1),
+            Bucket::Week => start + Days::new(7),
+            Bucket::Month => start + Months::new(1),
+        }
+    }
+}
+
+/// Sales and profit from `from` to `to`, both inclusive, per bucket and overall.
+///
+/// Every bucket in the range is listed, also those without invoices, so the
+/// report can be charted as is. The first and last bucket are cut off at
+/// `from` and `to`. Only invoices that are sales count, see [`Invoice::is_sale`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SalesReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub bucket: Bucket,
+    pub total: Summary,
+    pub buckets: Vec<BucketSummary>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BucketSummary {
+    pub start: NaiveDate,
+    /// The last day of the bucket, inclusive.
+    pub end: NaiveDate,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
+
+impl SalesReport {
+    pub fn of(invoices: &[Invoice], from: NaiveDate, to: NaiveDate, bucket: Bucket) -> Self {
+        let sales = sales_in(invoices, from, to);
+
+        let mut by_bucket: BTreeMap<NaiveDate, Vec<&Invoice>> = BTreeMap::new();
+        for invoice in &sales {
+            by_bucket
+                .entry(bucket.start_of(invoice.date))
+                .or_default()
+                .push(invoice);
+        }
+
+        let mut buckets = Vec::new();
+        let mut start = bucket.start_of(from);
+        while start <= to {
+            let next = bucket.next(start);
+            let invoices = by_bucket.remove(&start).unwrap_or_default();
+            buckets.push(BucketSummary {
+                start: start.max(from),
+                end: next
+                    .pred_opt()
+                    .expect("dates are far from the minimum")
+                    .min(to),
+                summary: Summary::of(invoices),
+            });
+            start = next;
+        }
+
+        Self {
+            from,
+            to,
+            bucket,
+            total: Summary::of(sales),
+            buckets,
+        }
+    }
+}
+
+/// The invoices dated from `from` to `to` that count as sales.
+fn sales_in(invoices: &[Invoice], from: NaiveDate, to: NaiveDate) -> Vec<&Invoice> {
+    invoices
+        .iter()
+        .filter(|invoice| invoice.is_sale())
+        .filter(|invoice| (from..=to).contains(&invoice.date))
+        .collect()
+}
+
+/// The sales from `from` to `to` per salesperson, `None` collecting the
+/// invoices without one.
+pub(crate) fn by_salesperson(
+    invoices: &[Invoice],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> BTreeMap<Option<SalespersonId>, Vec<&Invoice>> {
+    let mut by_salesperson: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for invoice in sales_in(invoices, from, to) {
+        by_salesperson
+            .entry(invoice.salesperson_id.clone())
+            .or_default()
+            .push(invoice);
+    }
+    by_salesperson
+}
+
+/// Sales and profit of every salesperson from `from` to `to`, best selling
+/// first. Only invoices that are sales count.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SalespersonReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub salespeople: Vec<SalespersonSummary>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SalespersonSummary {
+    /// `None` for the invoices without a salesperson.
+    pub salesperson_id: Option<SalespersonId>,
+    pub salesperson_name: Option<String>,
+    #[serde(flatten)]
+    pub summary: Summary,
+    /// At most [`TOP_CUSTOMERS`], by net sales.
+    pub top_customers: Vec<CustomerSales>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CustomerSales {
+    pub customer_id: CustomerId,
+    pub customer_name: String,
+    pub invoice_count: usize,
+    pub net_sales: Money,
+}
+
+impl SalespersonReport {
+    pub fn of(invoices: &[Invoice], from: NaiveDate, to: NaiveDate) -> Self {
+        let mut salespeople: Vec<_> = by_salesperson(invoices, from, to)
+            .into_iter()
+            .map(|(salesperson_id, invoices)| SalespersonSummary {
+                salesperson_id,
+                salesperson_name: invoices
+                    .iter()
+                    .find_map(|invoice| invoice.salesperson_name.clone()),
+                summary: Summary::of(invoices.iter().copied()),
+                top_customers: top_customers(&invoices),
+            })
+            .collect();
+        salespeople.sort_by(|a, b| b.summary.net_sales.cmp(&a.summary.net_sales));
+
+        Self {
+            from,
+            to,
+            salespeople,
+        }
+    }
+}
+
+fn top_customers(invoices: &[&Invoice]) -> Vec<CustomerSales> {
+    let mut by_customer: BTreeMap<&CustomerId, Vec<&Invoice>> = BTreeMap::new();
+    for invoice in invoices {
+        by_customer
+            .entry(&invoice.customer_id)
+            .or_default()
+            .push(invoice);
+    }
+
+    let mut customers: Vec<_> = by_customer
+        .into_iter()
+        .map(|(customer_id, invoices)| CustomerSales {
+            customer_id: customer_id.clone(),
+            customer_name: invoices[0].customer_name.clone(),
+            invoice_count: invoices.len(),
+            net_sales: Summary::of(invoices).net_sales,
+        })
+        .collect();
+    customers.sort_by(|a, b| b.net_sales.cmp(&a.net_sales));
+    customers.truncate(TOP_CUSTOMERS);
+    customers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Result;
+    use crate::zoho::InvoiceBuilder;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn buckets_start_on_day_monday_and_first_of_month() {
+        // a Wednesday
+        let day = date("2024-05-29");
+
+        assert_eq!(Bucket::Day.start_of(day), day);
+        assert_eq!(Bucket::Week.start_of(day), date("2024-05-27"));
+        assert_eq!(Bucket::Month.start_of(day), date("2024-05-01"));
+        assert_eq!(Bucket::Month.next(date("2024-01-01")), date("2024-02-01"));
+    }
+
+    #[test]
+    fn every_week_is_listed_and_cut_off_at_the_range() -> Result<()> {
+        let invoices = [
+            ("1", "2024-05-29", "paid"),
+            ("2", "2024-06-02", "sent"),
+            ("3", "2024-06-12", "sent"),
+            ("4", "2024-06-12", "void"),
+            ("5", "2024-06-20", "sent"),
+        ]
+        .into_iter()
+        .map(|(id, date, status)| {
+            InvoiceBuilder::new(id)
+                .date(date)
+                .status(status)
+                .purchase_rate(8.0)
+                .build()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        let report = SalesReport::of(
+            &invoices,
+            date("2024-05-29"),
+            date("2024-06-14"),
+            Bucket::Week,
+        );
+        let weeks: Vec<_> = report
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.start, bucket.end, bucket.summary.invoice_count))
+            .collect();
+
+        assert_eq!(
+            weeks,
+            [
+                (date("2024-05-29"), date("2024-06-02"), 2),
+                (date("2024-06-03"), date("2024-06-09"), 0),
+                (date("2024-06-10"), date("2024-06-14"), 1),
+            ]
+        );
+        assert_eq!(report.total.invoice_count, 3);
+        assert_eq!(report.total.profit, Some(60.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn salespeople_are_ranked_with_their_top_customers() -> Result<()> {
+        let invoices = [
+            ("1", "2024-05-27", "sent", Some("1"), "10"),
+            ("2", "2024-05-27", "sent", Some("2"), "10"),
+            ("3", "2024-05-28", "sent", Some("2"), "20"),
+            ("4", "2024-05-28", "sent", Some("2"), "20"),
+            ("5", "2024-05-28", "void", Some("1"), "10"),
+            ("6", "2024-05-28", "sent", None, "30"),
+        ]
+        .into_iter()
+        .map(|(id, date, status, salesperson_id, customer_id)| {
+            let invoice = InvoiceBuilder::new(id)
+                .date(date)
+                .status(status)
+                .customer(customer_id)
+                .purchase_rate(8.0);
+            match salesperson_id {
+                Some(salesperson_id) => invoice.salesperson(salesperson_id).build(),
+                None => invoice.build(),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        let report = SalespersonReport::of(&invoices, date("2024-05-27"), date("2024-05-31"));
+        let salespeople: Vec<_> = report
+            .salespeople
+            .iter()
+            .map(|s| {
+                (
+                    s.salesperson_id.as_ref().map(|id| id.as_str()),
+                    s.summary.invoice_count,
+                )
+            })
+            .collect();
+
+        assert_eq!(salespeople, [(Some("2"), 3), (None, 1), (Some("1"), 1)]);
+        let best = &report.salespeople[0];
+        assert_eq!(best.salesperson_name.as_deref(), Some("salesperson 2"));
+        assert_eq!(best.top_customers[0].customer_id.as_str(), "20");
+        assert_eq!(best.top_customers[0].invoice_count, 2);
+        assert_eq!(best.top_customers[0].net_sales, 200.into());
+        Ok(())
+    }
+}
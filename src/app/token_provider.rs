@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::database::{Cipher, Tokens};
+use crate::error::{Error, Result};
+use crate::zoho::{Client, Token};
+
+/// A tenant and scope, which together identify a stored token.
+type TokenKey = (Uuid, String);
+
+/// Hands out valid Zoho tokens of each tenant, refreshing them shortly before
+/// they expire.
+///
+/// Concurrent callers asking for the same token share a single refresh: the
+/// first one refreshes while the others wait and then read the new token. A
+/// Postgres advisory lock extends that guarantee across delivr instances.
+#[derive(Clone, Debug)]
+pub struct TokenProvider {
+    pool: PgPool,
+    cipher: Cipher,
+    client: Client,
+    /// The scope requested when connecting, see `zoho.scope`.
+    scope: String,
+    margin: chrono::Duration,
+    in_flight: Arc<Mutex<HashMap<TokenKey, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl TokenProvider {
+    pub fn new(
+        pool: PgPool,
+        cipher: Cipher,
+        client: Client,
+        scope: String,
+        margin: chrono::Duration,
+    ) -> Self {
+        Self {
+            pool,
+            cipher,
+            client,
+            scope,
+            margin,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The tenant's token for the configured scope.
+    pub async fn get(&self, tenant_id: Uuid) -> Result<Token> {
+        self.get_for_scope(tenant_id, &self.scope).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_for_scope(&self, tenant_id: Uuid, scope: &str) -> Result<Token> {
+        let token = self.load(tenant_id, scope).await?;
+        if !token.expires_within(self.margin) {
+            return Ok(token);
+        }
+
+        let lock = self.lock_for(tenant_id, scope);
+        let result = self.refresh_exclusive(&lock, tenant_id, scope).await;
+        self.release(lock, tenant_id, scope);
+
+        result
+    }
+
+    pub fn tokens(&self) -> Tokens<'_> {
+        Tokens {
+            pool: &self.pool,
+            cipher: &self.cipher,
+        }
+    }
+
+    async fn load(&self, tenant_id: Uuid, scope: &str) -> Result<Token> {
+        self.tokens()
+            .get_by_scope(tenant_id, scope)
+            .await?
+            .ok_or(Error::ZohoNotConnected)
+    }
+
+    fn lock_for(&self, tenant_id: Uuid, scope: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight
+            .entry((tenant_id, scope.to_string()))
+            .or_default()
+            .clone()
+    }
+
+    async fn refresh_exclusive(
+        &self,
+        lock: &tokio::sync::Mutex<()>,
+        tenant_id: Uuid,
+        scope: &str,
+    ) -> Result<Token> {
+        let _guard = lock.lock().await;
+
+        // another task may have refreshed while we were waiting
+        let token = self.load(tenant_id, scope).await?;
+        if !token.expires_within(self.margin) {
+            return Ok(token);
+        }
+
+        self.refresh_with_advisory_lock(tenant_id, scope).await
+    }
+
+    /// Drops the lock from the map once no other task holds or waits for it,
+    /// so the map does not keep every tenant and scope ever seen.
+    fn release(&self, lock: Arc<tokio::sync::Mutex<()>>, tenant_id: Uuid, scope: &str) {
+        drop(lock);
+
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let key = (tenant_id, scope.to_string());
+        if in_flight
+            .get(&key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            in_flight.remove(&key);
+        }
+    }
+
+    /// The lock belongs to a transaction, so it is released however the
+    /// refresh ends, even when the future is dropped half way.
+    async fn refresh_with_advisory_lock(&self, tenant_id: Uuid, scope: &str) -> Result<Token> {
+        let mut tx = self.pool.begin().await?;
+        let key = format!("{tenant_id}:{scope}");
+
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&key)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = self.refresh_locked(tenant_id, scope).await;
+
+        // the refresh is stored already, failing to release the lock must not hide it
+        if let Err(err) = tx.commit().await {
+            tracing::warn!("Failed to release the token lock: {err}");
+        }
+
+        result
+    }
+
+    async fn refresh_locked(&self, tenant_id: Uuid, scope: &str) -> Result<Token> {
+        // another instance may have refreshed while we held no lock
+        let token = self.load(tenant_id, scope).await?;
+        if !token.expires_within(self.margin) {
+            return Ok(token);
+        }
+
+        tracing::info!("Token is about to expire, refreshing token...");
+        let tokens = self.tokens();
+
+        let token = match self.client.refresh_token(&token).await {
+            Ok(token) => token,
+            Err(err) => {
+                let reauthorize = err.requires_reauthorization();
+                tokens
+                    .record_refresh_failure(tenant_id, scope, &err.to_string(), reauthorize)
+                    .await?;
+                return Err(err.into());
+            }
+        };
+
+        tokens.update(tenant_id, &token).await?;
+        tokens.record_refresh_success(tenant_id, scope).await?;
+
+        tracing::info!("Token has been refreshed");
+        Ok(token)
+    }
+}
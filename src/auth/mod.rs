@@ -0,0 +1,214 @@
+mod password;
+pub use password::{generate_secret, hash_password, hash_secret, verify_password};
+
+use std::marker::PhantomData;
+
+use axum::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::{header::AUTHORIZATION, request::Parts};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::database::{ApiKeys, Sessions};
+use crate::error::{Error, Result};
+
+pub const SESSION_COOKIE: &str = "session";
+/// How long a browser session stays valid after logging in.
+pub const SESSION_TTL_DAYS: i64 = 7;
+
+/// Roles from least to most privileged; every role can do what the ones
+/// before it can.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Sees the deliveries of the day, without costs or profit.
+    Driver,
+    /// Read-only access to sales figures, including profit.
+    Viewer,
+    /// Viewer plus the health of the Zoho connection.
+    Manager,
+    /// Everything, including connecting Zoho and managing users.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Driver => "driver",
+            Role::Viewer => "viewer",
+            Role::Manager => "manager",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn can_see_profit(&self) -> bool {
+        *self >= Role::Viewer
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "driver" => Ok(Self::Driver),
+            "viewer" => Ok(Self::Viewer),
+            "manager" => Ok(Self::Manager),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!(
+                "{} is not a supported role. Use `admin`, `manager`, `viewer` or `driver`.",
+                other
+            )),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Role {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Role::try_from(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct User {
+    pub id: Uuid,
+    /// Everything the user sees and does is scoped to this tenant.
+    pub tenant_id: Uuid,
+    pub email: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    pub fn require(&self, role: Role) -> Result<()> {
+        if self.role < role {
+            tracing::warn!(user = %self.email, required = role.as_str(), "role too low");
+            return Err(Error::Forbidden);
+        }
+
+        Ok(())
+    }
+}
+
+/// The user behind the request, identified by an API key in the
+/// `Authorization: Bearer` header or by the session cookie.
+#[async_trait]
+impl<S> FromRequestParts<S> for User
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        if let Some(user) = parts.extensions.get::<User>() {
+            return Ok(user.clone());
+        }
+
+        let state = AppState::from_ref(state);
+
+        let api_key = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        let user = if let Some(api_key) = api_key {
+            let api_keys = ApiKeys { pool: &state.pool };
+            api_keys.get_user(&hash_secret(api_key)).await?
+        } else if let Some(session) = CookieJar::from_headers(&parts.headers).get(SESSION_COOKIE) {
+            let sessions = Sessions { pool: &state.pool };
+            sessions.get_user(&hash_secret(session.value())).await?
+        } else {
+            None
+        };
+
+        let user = user.ok_or(Error::Unauthorized)?;
+
+        parts.extensions.insert(user.clone());
+        Ok(user)
+    }
+}
+
+/// A minimum role, used as `Require<roles::Manager>` in handler arguments.
+pub trait Permission {
+    const ROLE: Role;
+}
+
+pub mod roles {
+    use super::{Permission, Role};
+
+    pub struct Driver;
+    pub struct Viewer;
+    pub struct Manager;
+    pub struct Admin;
+
+    impl Permission for Driver {
+        const ROLE: Role = Role::Driver;
+    }
+
+    impl Permission for Viewer {
+        const ROLE: Role = Role::Viewer;
+    }
+
+    impl Permission for Manager {
+        const ROLE: Role = Role::Manager;
+    }
+
+    impl Permission for Admin {
+        const ROLE: Role = Role::Admin;
+    }
+}
+
+/// Rejects the request unless the user has at least the role `P`.
+pub struct Require<P> {
+    pub user: User,
+    permission: PhantomData<P>,
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for Require<P>
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+    P: Permission,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let user = User::from_request_parts(parts, state).await?;
+        user.require(P::ROLE)?;
+
+        Ok(Self {
+            user,
+            permission: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roles_are_ordered_by_privilege() {
+        assert!(Role::Admin > Role::Manager);
+        assert!(Role::Manager > Role::Viewer);
+        assert!(Role::Viewer > Role::Driver);
+
+        assert!(!Role::Driver.can_see_profit());
+        assert!(Role::Viewer.can_see_profit());
+    }
+
+    #[test]
+    fn role_from_string() {
+        assert_eq!(Role::try_from("Admin".to_string()), Ok(Role::Admin));
+        assert!(Role::try_from("owner".to_string()).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::utils::to_hex;
+
+/// Hashes with Argon2id on the blocking pool, hashing is deliberately slow.
+pub async fn hash_password(password: Secret<String>) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.expose_secret().as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|_| Error::custom("Failed to hash password"))
+    })
+    .await
+    .map_err(|_| Error::custom("Password hashing task failed"))?
+}
+
+pub async fn verify_password(password: Secret<String>, hash: String) -> Result<bool> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&hash)
+            .map_err(|_| Error::custom("Stored password hash is invalid"))?;
+
+        Ok(Argon2::default()
+            .verify_password(password.expose_secret().as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|_| Error::custom("Password hashing task failed"))?
+}
+
+/// A random token for session cookies and API keys.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// Session tokens and API keys are looked up by their digest, so a leaked
+/// database does not hand out working credentials.
+pub fn hash_secret(secret: &str) -> String {
+    to_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn password_round_trip() -> Result<()> {
+        let hash = hash_password(Secret::new("correct horse".to_string())).await?;
+
+        assert!(verify_password(Secret::new("correct horse".to_string()), hash.clone()).await?);
+        assert!(!verify_password(Secret::new("battery staple".to_string()), hash).await?);
+
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 
 use crate::error::{Error, Result};
+use crate::zoho::Region;
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Config {
@@ -11,6 +15,10 @@ pub struct Config {
     pub environment: Environment,
     pub database: Database,
     pub zoho: Zoho,
+    pub encryption: Encryption,
+    pub mirror: Mirror,
+    pub cache: Cache,
+    pub organizations: Organizations,
 }
 
 impl Config {
@@ -25,6 +33,22 @@ pub struct Application {
     pub port: u16,
     pub host: String,
     pub base_url: String,
+    /// Key used to sign values handed to the browser, such as the OAuth `state`.
+    pub secret: Secret<String>,
+    /// Where the business operates, e.g. `Asia/Kuala_Lumpur`. Business dates,
+    /// including what counts as "today", are taken in this timezone.
+    pub timezone: Tz,
+}
+
+impl Application {
+    /// The business date at the given instant.
+    pub fn business_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.timezone).date_naive()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.business_date(Utc::now())
+    }
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -67,6 +91,94 @@ impl Database {
 pub struct Zoho {
     pub client_id: String,
     pub client_secret: Secret<String>,
+    /// Comma separated scopes requested on the consent screen.
+    pub scope: String,
+    /// Where Zoho sends the user back to; defaults to `{base_url}/oauth/callback`.
+    pub redirect_uri: Option<String>,
+    /// Data centre of the Zoho account, e.g. `com`, `eu`, `in`.
+    pub region: Region,
+    /// Overrides the accounts server derived from `region`, e.g. to point at a mock Zoho.
+    pub accounts_url: Option<String>,
+    /// Overrides the API domain Zoho hands back with each token.
+    pub api_url: Option<String>,
+    /// Maximum number of invoice detail requests in flight at once.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: usize,
+    /// Client-side cap on requests sent to Zoho, to stay under the Books API limit.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub requests_per_minute: u32,
+    pub retry: Retry,
+    /// Tokens are refreshed once they are this close to expiring.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub refresh_margin_secs: i64,
+    /// How often the background refresher checks every stored token.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub refresh_interval_secs: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Retry {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_ms: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_ms: u64,
+}
+
+/// The local copy of Zoho invoices, see `app::Mirror`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Mirror {
+    /// Organizations kept in sync in the background.
+    #[serde(default)]
+    pub organization_ids: Vec<String>,
+    /// How often changes are pulled from Zoho.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interval_secs: u64,
+    /// How often the full list of invoice IDs is compared to find deleted invoices.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub reconcile_interval_secs: i64,
+    /// How long after its last sync the mirror of an organization is still
+    /// read from; past that it has fallen behind and reads go to Zoho.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub freshness_secs: i64,
+}
+
+/// The in-process cache of Zoho responses, see `app::InvoiceCache`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Cache {
+    /// How long invoices of today, or of ranges reaching today, stay fresh.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub today_ttl_secs: u64,
+    /// How long invoices of past business dates stay fresh.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub past_ttl_secs: u64,
+    /// How long after going stale an entry is still served while it is
+    /// refreshed in the background.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub stale_secs: u64,
+}
+
+/// The stored list of each tenant's organizations, see `app::OrganizationDirectory`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Organizations {
+    /// How long the stored list is used before Zoho is asked again.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ttl_secs: i64,
+    /// The least time between two listings of one tenant, however many
+    /// unknown organizations it asks for.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub refresh_interval_secs: u64,
+}
+
+/// Master keys for encrypting secrets at rest, as 32 bytes of hex keyed by id.
+///
+/// To rotate, add a new key, make it active and run `delivr reencrypt-tokens`;
+/// the old key can be removed once no rows refer to it.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Encryption {
+    pub active_key_id: String,
+    pub keys: HashMap<String, Secret<String>>,
 }
 
 pub fn get_config() -> Result<Config> {
@@ -138,3 +250,27 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn business_date_follows_the_timezone() {
+        let application = Application {
+            port: 8000,
+            host: "127.0.0.1".to_string(),
+            base_url: "http://127.0.0.1:8000".to_string(),
+            secret: Secret::new("secret".to_string()),
+            timezone: chrono_tz::Asia::Kuala_Lumpur,
+        };
+
+        // 17:00 UTC is already past midnight in Kuala Lumpur
+        let at = "2024-05-27T17:00:00Z".parse().unwrap();
+
+        assert_eq!(
+            application.business_date(at),
+            NaiveDate::from_ymd_opt(2024, 5, 28).unwrap()
+        );
+    }
+}
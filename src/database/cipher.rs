@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::config;
+use crate::error::{Error, Result};
+use crate::utils::from_hex;
+
+const NONCE_LEN: usize = 12;
+
+/// Envelope encryption for secrets stored in Postgres.
+///
+/// Every sealed value gets its own random data key, which is itself encrypted
+/// ("wrapped") with a master key from config. Rows remember the id of the
+/// master key that wrapped them, so master keys can be rotated by adding a new
+/// key, making it active and re-encrypting.
+#[derive(Clone)]
+pub struct Cipher {
+    active_key_id: String,
+    keys: Arc<HashMap<String, Key<Aes256Gcm>>>,
+}
+
+/// A data key wrapped by the master key `key_id`.
+pub struct DataKey {
+    pub key_id: String,
+    pub wrapped: Vec<u8>,
+    key: Key<Aes256Gcm>,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher")
+            .field("active_key_id", &self.active_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    pub fn from_config(config: &config::Encryption) -> Result<Self> {
+        let mut keys = HashMap::new();
+
+        for (id, hex) in &config.keys {
+            let bytes = from_hex(hex.expose_secret())
+                .filter(|bytes| bytes.len() == 32)
+                .ok_or(Error::custom(format!(
+                    "Encryption key {id} must be 32 bytes of hex"
+                )))?;
+            keys.insert(id.clone(), *Key::<Aes256Gcm>::from_slice(&bytes));
+        }
+
+        if !keys.contains_key(&config.active_key_id) {
+            return Err(Error::custom(format!(
+                "Active encryption key {} is not configured",
+                config.active_key_id
+            )));
+        }
+
+        Ok(Self {
+            active_key_id: config.active_key_id.clone(),
+            keys: Arc::new(keys),
+        })
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    fn master_key(&self, key_id: &str) -> Result<&Key<Aes256Gcm>> {
+        self.keys
+            .get(key_id)
+            .ok_or(Error::custom(format!("Unknown encryption key {key_id}")))
+    }
+
+    /// A fresh data key wrapped with the active master key.
+    pub fn new_data_key(&self) -> Result<DataKey> {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let master = self.master_key(&self.active_key_id)?;
+        let wrapped = encrypt(master, &key, self.active_key_id.as_bytes())?;
+
+        Ok(DataKey {
+            key_id: self.active_key_id.clone(),
+            wrapped,
+            key,
+        })
+    }
+
+    pub fn unwrap_data_key(&self, key_id: &str, wrapped: &[u8]) -> Result<DataKey> {
+        let master = self.master_key(key_id)?;
+        let key = decrypt(master, wrapped, key_id.as_bytes())?;
+
+        Ok(DataKey {
+            key_id: key_id.to_string(),
+            wrapped: wrapped.to_vec(),
+            key: *Key::<Aes256Gcm>::from_slice(&key),
+        })
+    }
+}
+
+impl DataKey {
+    /// `aad` binds the ciphertext to its row so values cannot be swapped between rows.
+    pub fn seal(&self, plaintext: &Secret<String>, aad: &str) -> Result<Vec<u8>> {
+        encrypt(
+            &self.key,
+            plaintext.expose_secret().as_bytes(),
+            aad.as_bytes(),
+        )
+    }
+
+    pub fn open(&self, sealed: &[u8], aad: &str) -> Result<Secret<String>> {
+        let plaintext = decrypt(&self.key, sealed, aad.as_bytes())?;
+        let plaintext = String::from_utf8(plaintext)
+            .map_err(|_| Error::custom("Decrypted secret is not UTF-8"))?;
+
+        Ok(Secret::new(plaintext))
+    }
+}
+
+/// Returns the nonce followed by the ciphertext.
+fn encrypt(key: &Key<Aes256Gcm>, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::custom("Failed to encrypt"))?;
+
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(Error::custom("Ciphertext is too short"));
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(key)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::custom("Failed to decrypt"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cipher(active: &str) -> Cipher {
+        Cipher::from_config(&config::Encryption {
+            active_key_id: active.to_string(),
+            keys: HashMap::from([
+                ("old".to_string(), Secret::new("11".repeat(32))),
+                ("new".to_string(), Secret::new("22".repeat(32))),
+            ]),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn seal_and_open() -> Result<()> {
+        let cipher = cipher("new");
+        let data_key = cipher.new_data_key()?;
+        let sealed = data_key.seal(&Secret::new("secret".to_string()), "scope")?;
+
+        let data_key = cipher.unwrap_data_key(&data_key.key_id, &data_key.wrapped)?;
+        assert_eq!(data_key.open(&sealed, "scope")?.expose_secret(), "secret");
+        assert!(data_key.open(&sealed, "other scope").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotated_keys_still_open() -> Result<()> {
+        let old = cipher("old").new_data_key()?;
+
+        let rotated = cipher("new");
+        assert!(rotated.unwrap_data_key(&old.key_id, &old.wrapped).is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use sqlx::types::Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::app::CommissionPlan;
+use crate::error::{Error, Result};
+
+/// The commission plan of each tenant, stored as JSON as it is only ever
+/// read and written whole.
+pub struct CommissionPlans<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> CommissionPlans<'a> {
+    pub async fn get(&self, tenant_id: Uuid) -> Result<Option<CommissionPlan>> {
+        let query = "SELECT plan FROM commission_plans WHERE tenant_id = $1";
+
+        let mut conn = self.pool.acquire().await?;
+        let plan: Option<Json<CommissionPlan>> = sqlx::query_scalar(query)
+            .bind(tenant_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(plan.map(|Json(plan)| plan))
+    }
+
+    pub async fn save(&self, tenant_id: Uuid, plan: &CommissionPlan) -> Result<()> {
+        let query = r#"
+            INSERT INTO commission_plans (tenant_id, plan)
+            VALUES ($1, $2)
+            ON CONFLICT (tenant_id) DO UPDATE
+            SET plan = EXCLUDED.plan, updated_at = now()
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(tenant_id)
+            .bind(Json(plan))
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+}
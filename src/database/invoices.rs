@@ -0,0 +1,348 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::zoho::{Invoice, InvoiceId, Query, SortColumn, SortOrder};
+
+/// The mirrored invoices, see `app::Mirror`.
+pub struct Invoices<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> Invoices<'a> {
+    /// Stores the invoice with its customer and line items, replacing any
+    /// earlier copy. `payload` is the invoice exactly as Zoho sent it.
+    pub async fn upsert(
+        &self,
+        organization_id: &str,
+        invoice: &Invoice,
+        payload: &Value,
+    ) -> Result<()> {
+        let status = serde_json::to_value(invoice.status)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO customers (organization_id, customer_id, customer_name)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (organization_id, customer_id)
+            DO UPDATE SET customer_name = EXCLUDED.customer_name, synced_at = now()
+            "#,
+        )
+        .bind(organization_id)
+        .bind(invoice.customer_id.as_str())
+        .bind(&invoice.customer_name)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO invoices (organization_id, invoice_id, invoice_number, status, date, customer_id,
+                salesperson_id, salesperson_name, currency_code, total, balance, created_time,
+                last_modified_time, payload)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            ON CONFLICT (organization_id, invoice_id)
+            DO UPDATE SET invoice_number = EXCLUDED.invoice_number, status = EXCLUDED.status,
+                date = EXCLUDED.date, customer_id = EXCLUDED.customer_id,
+                salesperson_id = EXCLUDED.salesperson_id, salesperson_name = EXCLUDED.salesperson_name,
+                currency_code = EXCLUDED.currency_code, total = EXCLUDED.total,
+                balance = EXCLUDED.balance, created_time = EXCLUDED.created_time,
+                last_modified_time = EXCLUDED.last_modified_time, payload = EXCLUDED.payload,
+                synced_at = now(), deleted_at = NULL
+            "#,
+        )
+        .bind(organization_id)
+        .bind(invoice.invoice_id.as_str())
+        .bind(&invoice.invoice_number)
+        .bind(status.as_str())
+        .bind(invoice.date)
+        .bind(invoice.customer_id.as_str())
+        .bind(invoice.salesperson_id.as_ref().map(|id| id.as_str()))
+        .bind(&invoice.salesperson_name)
+        .bind(&invoice.currency_code)
+        .bind(invoice.total.amount())
+        .bind(invoice.balance.amount())
+        .bind(invoice.created_time)
+        .bind(invoice.last_modified_time)
+        .bind(payload)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM line_items WHERE organization_id = $1 AND invoice_id = $2")
+            .bind(organization_id)
+            .bind(invoice.invoice_id.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        for (position, line) in invoice.line_items.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO line_items (organization_id, invoice_id, line_item_id, position, item_id,
+                    name, quantity, rate, purchase_rate, item_total)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
+            )
+            .bind(organization_id)
+            .bind(invoice.invoice_id.as_str())
+            .bind(line.line_item_id.as_str())
+            .bind(position as i32)
+            .bind(line.item_id.as_ref().map(|id| id.as_str()))
+            .bind(&line.name)
+            .bind(&line.quantity)
+            .bind(line.rate.amount())
+            .bind(line.purchase_rate.as_ref().map(|rate| rate.amount()))
+            .bind(line.item_total.amount())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Flags every invoice not in `present` as deleted in Zoho and returns how many were flagged.
+    pub async fn mark_missing_deleted(
+        &self,
+        organization_id: &str,
+        present: &[InvoiceId],
+    ) -> Result<u64> {
+        let query = r#"
+            UPDATE invoices
+            SET deleted_at = now()
+            WHERE organization_id = $1 AND deleted_at IS NULL AND NOT (invoice_id = ANY($2))
+        "#;
+
+        let present: Vec<&str> = present.iter().map(InvoiceId::as_str).collect();
+
+        let mut conn = self.pool.acquire().await?;
+        let res = sqlx::query(query)
+            .bind(organization_id)
+            .bind(&present)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn get(&self, organization_id: &str, id: &InvoiceId) -> Result<Option<Invoice>> {
+        let query = r#"
+            SELECT payload
+            FROM invoices
+            WHERE organization_id = $1 AND invoice_id = $2 AND deleted_at IS NULL
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let payload: Option<Value> = sqlx::query_scalar(query)
+            .bind(organization_id)
+            .bind(id.as_str())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(payload.map(Invoice::try_from).transpose()?)
+    }
+
+    /// The invoices matching the filters of `query`, the way Zoho would list them.
+    /// Paging is ignored, every match is returned.
+    pub async fn find(&self, query: &Query<'_>) -> Result<Vec<Invoice>> {
+        let mut sql = QueryBuilder::<Postgres>::new(
+            "SELECT i.payload FROM invoices i JOIN customers c \
+             ON c.organization_id = i.organization_id AND c.customer_id = i.customer_id \
+             WHERE i.deleted_at IS NULL AND i.organization_id = ",
+        );
+        sql.push_bind(query.organization_id);
+
+        if let Some(date) = query.date {
+            sql.push(" AND i.date = ").push_bind(date);
+        }
+        if let Some(date_start) = query.date_start {
+            sql.push(" AND i.date >= ").push_bind(date_start);
+        }
+        if let Some(date_end) = query.date_end {
+            sql.push(" AND i.date <= ").push_bind(date_end);
+        }
+        if let Some(status) = query.status {
+            let statuses = status
+                .statuses()
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<Vec<_>>>()?;
+            let statuses: Vec<String> = statuses
+                .iter()
+                .filter_map(|status| status.as_str().map(str::to_string))
+                .collect();
+            sql.push(" AND i.status = ANY(")
+                .push_bind(statuses)
+                .push(")");
+        }
+        if let Some(customer_id) = query.customer_id {
+            sql.push(" AND i.customer_id = ")
+                .push_bind(customer_id.as_str());
+        }
+        if let Some(salesperson_id) = query.salesperson_id {
+            sql.push(" AND i.salesperson_id = ")
+                .push_bind(salesperson_id.as_str());
+        }
+        if let Some(since) = query.last_modified_time {
+            sql.push(" AND i.last_modified_time >= ").push_bind(since);
+        }
+        if let Some(text) = query.search_text {
+            let pattern = format!("%{}%", text.replace('%', "\\%").replace('_', "\\_"));
+            sql.push(" AND (i.invoice_number ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR c.customer_name ILIKE ")
+                .push_bind(pattern)
+                .push(")");
+        }
+
+        let column = match query.sort_column {
+            Some(SortColumn::CustomerName) => "c.customer_name",
+            Some(SortColumn::InvoiceNumber) => "i.invoice_number",
+            Some(SortColumn::Date) => "i.date",
+            Some(SortColumn::DueDate) => "i.payload->>'due_date'",
+            Some(SortColumn::Total) => "i.total",
+            Some(SortColumn::Balance) => "i.balance",
+            Some(SortColumn::LastModifiedTime) => "i.last_modified_time",
+            Some(SortColumn::CreatedTime) | None => "i.created_time",
+        };
+        let order = match query.sort_order {
+            Some(SortOrder::Descending) => "DESC",
+            Some(SortOrder::Ascending) | None => "ASC",
+        };
+        sql.push(format!(" ORDER BY {column} {order}, i.invoice_id"));
+
+        let mut conn = self.pool.acquire().await?;
+        let payloads: Vec<Value> = sql
+            .build_query_scalar()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        payloads
+            .into_iter()
+            .map(|payload| Ok(Invoice::try_from(payload)?))
+            .collect()
+    }
+}
+
+/// How far the mirror of an organization has got.
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct SyncCursor {
+    pub organization_id: String,
+    /// The tenant whose token last synced the organization.
+    #[serde(skip)]
+    pub tenant_id: Uuid,
+    pub last_modified_time: Option<DateTime<Utc>>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub reconciled_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+pub struct SyncCursors<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> SyncCursors<'a> {
+    pub async fn get(&self, organization_id: &str) -> Result<Option<SyncCursor>> {
+        let query = r#"
+            SELECT organization_id, tenant_id, last_modified_time, last_synced_at, reconciled_at,
+                last_error
+            FROM sync_cursors
+            WHERE organization_id = $1
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let cursor = sqlx::query_as::<_, SyncCursor>(query)
+            .bind(organization_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(cursor)
+    }
+
+    /// The cursors of the organizations the tenant synced.
+    pub async fn get_all(&self, tenant_id: Uuid) -> Result<Vec<SyncCursor>> {
+        let query = r#"
+            SELECT organization_id, tenant_id, last_modified_time, last_synced_at, reconciled_at,
+                last_error
+            FROM sync_cursors
+            WHERE tenant_id = $1
+            ORDER BY organization_id
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let cursors = sqlx::query_as::<_, SyncCursor>(query)
+            .bind(tenant_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(cursors)
+    }
+
+    /// Records a successful sync with the tenant's token. `reconciled` is set
+    /// when deletions were looked for.
+    pub async fn record_success(
+        &self,
+        tenant_id: Uuid,
+        organization_id: &str,
+        last_modified_time: Option<DateTime<Utc>>,
+        reconciled: bool,
+    ) -> Result<()> {
+        let query = r#"
+            INSERT INTO sync_cursors (organization_id, tenant_id, last_modified_time,
+                last_synced_at, reconciled_at)
+            VALUES ($1, $4, $2, now(), CASE WHEN $3 THEN now() END)
+            ON CONFLICT (organization_id)
+            DO UPDATE SET tenant_id = EXCLUDED.tenant_id,
+                last_modified_time = EXCLUDED.last_modified_time,
+                last_synced_at = now(),
+                reconciled_at = COALESCE(EXCLUDED.reconciled_at, sync_cursors.reconciled_at),
+                last_error = NULL
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(organization_id)
+            .bind(last_modified_time)
+            .bind(reconciled)
+            .bind(tenant_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    /// Records a failed sync, unless the organization is another tenant's.
+    pub async fn record_failure(
+        &self,
+        tenant_id: Uuid,
+        organization_id: &str,
+        error: &str,
+    ) -> Result<()> {
+        let query = r#"
+            INSERT INTO sync_cursors (organization_id, tenant_id, last_error)
+            VALUES ($1, $3, $2)
+            ON CONFLICT (organization_id)
+            DO UPDATE SET last_error = EXCLUDED.last_error
+            WHERE sync_cursors.tenant_id = EXCLUDED.tenant_id
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(organization_id)
+            .bind(error)
+            .bind(tenant_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+}
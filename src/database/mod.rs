@@ -1,5 +1,23 @@
+mod cipher;
+pub use cipher::{Cipher, DataKey};
+
+mod commission_plans;
+pub use commission_plans::CommissionPlans;
+
+mod invoices;
+pub use invoices::{Invoices, SyncCursor, SyncCursors};
+
+mod organizations;
+pub use organizations::Organizations;
+
+mod tenants;
+pub use tenants::{Tenant, Tenants, DEFAULT_TENANT};
+
 mod tokens;
-pub use tokens::Tokens;
+pub use tokens::{TokenHealth, TokenStatus, Tokens};
+
+mod users;
+pub use users::{ApiKeys, Sessions, Users};
 
 use crate::error::{Error, Result};
 use sqlx::PgPool;
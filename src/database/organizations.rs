@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::zoho::Organization;
+
+/// The Zoho organizations of each tenant, as last listed by Zoho.
+pub struct Organizations<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> Organizations<'a> {
+    /// Replaces the tenant's organizations with `organizations`.
+    pub async fn replace_all(&self, tenant_id: Uuid, organizations: &[Organization]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // concurrent listings of one tenant take turns, otherwise both delete
+        // nothing and the second insert conflicts with the first
+        sqlx::query("SELECT id FROM tenants WHERE id = $1 FOR UPDATE")
+            .bind(tenant_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM organizations WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for organization in organizations {
+            sqlx::query(
+                r#"
+                INSERT INTO organizations (tenant_id, organization_id, name, is_default_org,
+                    currency_code, time_zone)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(&organization.organization_id)
+            .bind(&organization.name)
+            .bind(organization.is_default_org)
+            .bind(&organization.currency_code)
+            .bind(&organization.time_zone)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_all(&self, tenant_id: Uuid) -> Result<Vec<Organization>> {
+        let query = r#"
+            SELECT organization_id, name, is_default_org, currency_code, time_zone
+            FROM organizations
+            WHERE tenant_id = $1
+            ORDER BY name, organization_id
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query_as::<_, (String, String, bool, String, Option<String>)>(query)
+            .bind(tenant_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(organization_id, name, is_default_org, currency_code, time_zone)| Organization {
+                    organization_id,
+                    name,
+                    is_default_org,
+                    currency_code,
+                    time_zone,
+                },
+            )
+            .collect())
+    }
+
+    /// When the tenant's organizations were last listed, `None` if never or
+    /// if Zoho listed none.
+    pub async fn synced_at(&self, tenant_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        let query = "SELECT min(synced_at) FROM organizations WHERE tenant_id = $1";
+
+        let mut conn = self.pool.acquire().await?;
+        let synced_at = sqlx::query_scalar(query)
+            .bind(tenant_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(synced_at)
+    }
+
+    pub async fn contains(&self, tenant_id: Uuid, organization_id: &str) -> Result<bool> {
+        let query = r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM organizations
+                WHERE tenant_id = $1 AND organization_id = $2
+            )
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let exists: bool = sqlx::query_scalar(query)
+            .bind(tenant_id)
+            .bind(organization_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(exists)
+    }
+}
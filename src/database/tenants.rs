@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// The tenant that data from before tenants existed was given to.
+pub const DEFAULT_TENANT: &str = "default";
+
+const TENANT_COLUMNS: &str = "id, name, timezone, default_organization_id, created_at";
+
+/// A business using delivr, together with its settings.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Tenant {
+    pub id: Uuid,
+    pub name: String,
+    /// Overrides `application.timezone` for the tenant's business dates.
+    pub timezone: Option<Tz>,
+    /// Used when a request does not name an organization.
+    pub default_organization_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TenantRow {
+    id: Uuid,
+    name: String,
+    timezone: Option<String>,
+    default_organization_id: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<TenantRow> for Tenant {
+    type Error = Error;
+
+    fn try_from(row: TenantRow) -> Result<Self> {
+        let timezone = row
+            .timezone
+            .map(|timezone| timezone.parse::<Tz>().map_err(Error::custom))
+            .transpose()?;
+
+        Ok(Tenant {
+            id: row.id,
+            name: row.name,
+            timezone,
+            default_organization_id: row.default_organization_id,
+            created_at: row.created_at,
+        })
+    }
+}
+
+pub struct Tenants<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> Tenants<'a> {
+    pub async fn insert(&self, name: &str) -> Result<Tenant> {
+        let query = format!(
+            r#"
+            INSERT INTO tenants (id, name)
+            VALUES ($1, $2)
+            RETURNING {TENANT_COLUMNS}
+        "#
+        );
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, TenantRow>(&query)
+            .bind(Uuid::new_v4())
+            .bind(name.trim())
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.try_into()
+    }
+
+    pub async fn get_by_id(&self, id: Uuid) -> Result<Option<Tenant>> {
+        let query = format!("SELECT {TENANT_COLUMNS} FROM tenants WHERE id = $1");
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, TenantRow>(&query)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(Tenant::try_from).transpose()
+    }
+
+    pub async fn get_by_name(&self, name: &str) -> Result<Option<Tenant>> {
+        let query = format!("SELECT {TENANT_COLUMNS} FROM tenants WHERE name = $1");
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, TenantRow>(&query)
+            .bind(name.trim())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(Tenant::try_from).transpose()
+    }
+
+    /// `None` keeps a setting as it is, `Some(None)` clears it.
+    pub async fn update_settings(
+        &self,
+        id: Uuid,
+        timezone: Option<Option<Tz>>,
+        default_organization_id: Option<Option<&str>>,
+    ) -> Result<Tenant> {
+        let query = format!(
+            r#"
+            UPDATE tenants
+            SET timezone = CASE WHEN $2 THEN $3 ELSE timezone END,
+                default_organization_id = CASE WHEN $4 THEN $5 ELSE default_organization_id END
+            WHERE id = $1
+            RETURNING {TENANT_COLUMNS}
+        "#
+        );
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, TenantRow>(&query)
+            .bind(id)
+            .bind(timezone.is_some())
+            .bind(timezone.flatten().map(|timezone| timezone.name()))
+            .bind(default_organization_id.is_some())
+            .bind(default_organization_id.flatten())
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.try_into()
+    }
+}
@@ -1,24 +1,105 @@
+use crate::database::Cipher;
 use crate::error::{Error, Result};
 use crate::zoho::Token;
-use secrecy::ExposeSecret;
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
 use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Everything about a stored token except its secrets.
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct TokenHealth {
+    pub tenant_id: Uuid,
+    pub scope: String,
+    pub api_domain: String,
+    pub expires_at: DateTime<Utc>,
+    pub last_refresh_attempt_at: Option<DateTime<Utc>>,
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+    pub last_refresh_error: Option<String>,
+    pub reauthorization_required: bool,
+    #[sqlx(skip)]
+    pub status: TokenStatus,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenStatus {
+    #[default]
+    Valid,
+    Expired,
+    ReauthorizationRequired,
+}
+
+impl TokenHealth {
+    fn with_status(mut self) -> Self {
+        self.status = if self.reauthorization_required {
+            TokenStatus::ReauthorizationRequired
+        } else if self.expires_at < Utc::now() {
+            TokenStatus::Expired
+        } else {
+            TokenStatus::Valid
+        };
+        self
+    }
+}
 
 pub struct Tokens<'a> {
     pub pool: &'a PgPool,
+    pub cipher: &'a Cipher,
+}
+
+/// A row as stored: secrets are sealed with `cipher`, or still in plaintext
+/// for rows written before encryption that have not been re-encrypted yet.
+#[derive(sqlx::FromRow)]
+struct TokenRow {
+    tenant_id: Uuid,
+    scope: String,
+    api_domain: String,
+    expires_in: i64,
+    token_type: String,
+    time_stamp: DateTime<Utc>,
+    key_id: Option<String>,
+    wrapped_key: Option<Vec<u8>>,
+    access_token_sealed: Option<Vec<u8>>,
+    refresh_token_sealed: Option<Vec<u8>>,
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    /// Whether the secrets were sealed with [`aad`], rather than with the
+    /// scope alone as before tenants.
+    sealed_with_tenant: bool,
+}
+
+const TOKEN_COLUMNS: &str =
+    "tenant_id, scope, api_domain, expires_in, token_type, time_stamp, key_id, wrapped_key, \
+    access_token_sealed, refresh_token_sealed, access_token, refresh_token, sealed_with_tenant";
+
+/// Binds sealed secrets to their row, so they cannot be moved to another
+/// tenant's or scope's token.
+fn aad(tenant_id: Uuid, scope: &str) -> String {
+    format!("{tenant_id}:{scope}")
+}
+
+/// Sealed secrets of a token, ready to be written.
+struct SealedToken {
+    key_id: String,
+    wrapped_key: Vec<u8>,
+    access_token: Vec<u8>,
+    refresh_token: Option<Vec<u8>>,
 }
 
 impl<'a> Tokens<'a> {
-    pub async fn contains_scope(&self, scope: &str) -> Result<bool> {
+    pub async fn contains_scope(&self, tenant_id: Uuid, scope: &str) -> Result<bool> {
         let query = r#"
             SELECT EXISTS (
                 SELECT 1
                 FROM tokens
-                WHERE scope = $1
+                WHERE tenant_id = $1 AND scope = $2
             )
         "#;
 
         let mut conn = self.pool.acquire().await?;
         let res = sqlx::query(query)
+            .bind(tenant_id)
             .bind(scope)
             .fetch_one(&mut *conn)
             .await
@@ -27,24 +108,26 @@ impl<'a> Tokens<'a> {
         Ok(res.get::<bool, _>(0))
     }
 
-    pub async fn insert(&self, token: &Token) -> Result<()> {
+    pub async fn insert(&self, tenant_id: Uuid, token: &Token) -> Result<()> {
         let query = r#"
-            INSERT INTO tokens (access_token, api_domain, expires_in, refresh_token, scope, token_type, time_stamp)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO tokens (access_token_sealed, api_domain, expires_in, refresh_token_sealed, scope, token_type, time_stamp, key_id, wrapped_key, tenant_id, sealed_with_tenant)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, TRUE)
         "#;
 
-        let access_token = token.access_token.expose_secret();
-        let refresh_token = token.refresh_token.as_ref().map(|rt| rt.expose_secret());
+        let sealed = self.seal(tenant_id, token)?;
 
         let mut conn = self.pool.acquire().await?;
         let res = sqlx::query(query)
-            .bind(access_token)
+            .bind(sealed.access_token)
             .bind(&token.api_domain)
             .bind(token.expires_in)
-            .bind(refresh_token)
+            .bind(sealed.refresh_token)
             .bind(&token.scope)
             .bind(&token.token_type)
             .bind(token.time_stamp)
+            .bind(sealed.key_id)
+            .bind(sealed.wrapped_key)
+            .bind(tenant_id)
             .execute(&mut *conn)
             .await
             .map_err(Error::from)?;
@@ -56,35 +139,37 @@ impl<'a> Tokens<'a> {
         Ok(())
     }
 
-    pub async fn get_by_scope(&self, scope: &str) -> Result<Option<Token>> {
-        let query = r#"
-            SELECT access_token, api_domain, expires_in, refresh_token, scope, token_type, time_stamp
-            FROM tokens
-            WHERE scope = $1
-        "#;
+    /// Inserts the token, replacing any existing token of the tenant for the same scope.
+    pub async fn save(&self, tenant_id: Uuid, token: &Token) -> Result<()> {
+        if self.contains_scope(tenant_id, &token.scope).await? {
+            tracing::warn!("token already exists, replacing the existing token");
+            self.update(tenant_id, token).await
+        } else {
+            self.insert(tenant_id, token).await
+        }
+    }
+
+    pub async fn get_by_scope(&self, tenant_id: Uuid, scope: &str) -> Result<Option<Token>> {
+        let query =
+            format!("SELECT {TOKEN_COLUMNS} FROM tokens WHERE tenant_id = $1 AND scope = $2");
 
         let mut conn = self.pool.acquire().await?;
-        let res = sqlx::query_as::<_, Token>(query)
+        let res = sqlx::query_as::<_, TokenRow>(&query)
+            .bind(tenant_id)
             .bind(scope)
             .fetch_optional(&mut *conn)
             .await
             .map_err(Error::from)?;
 
-        if res.is_none() {
-            return Ok(None);
-        }
-
-        Ok(res)
+        res.map(|row| self.open(row)).transpose()
     }
 
-    pub async fn get_all(&self) -> Result<Vec<Token>> {
-        let query = r#"
-            SELECT access_token, api_domain, expires_in, refresh_token, scope, token_type, time_stamp
-            FROM tokens
-        "#;
+    pub async fn get_all(&self, tenant_id: Uuid) -> Result<Vec<Token>> {
+        let query = format!("SELECT {TOKEN_COLUMNS} FROM tokens WHERE tenant_id = $1");
 
         let mut conn = self.pool.acquire().await?;
-        let res = sqlx::query_as::<_, Token>(query)
+        let res = sqlx::query_as::<_, TokenRow>(&query)
+            .bind(tenant_id)
             .fetch_all(&mut *conn)
             .await;
 
@@ -93,28 +178,34 @@ impl<'a> Tokens<'a> {
             return Err(Error::Sqlx(err));
         }
 
-        Ok(res.unwrap())
+        res.unwrap().into_iter().map(|row| self.open(row)).collect()
     }
 
-    pub async fn update(&self, token: &Token) -> Result<()> {
+    /// Replaces the stored token. A new token works, so whatever went wrong
+    /// refreshing the old one no longer applies.
+    pub async fn update(&self, tenant_id: Uuid, token: &Token) -> Result<()> {
         let query = r#"
             UPDATE tokens
-            SET access_token = $1, api_domain = $2, expires_in = $3, refresh_token = $4, scope = $5, token_type = $6, time_stamp = $7
-            WHERE scope = $5
+            SET access_token_sealed = $1, api_domain = $2, expires_in = $3, refresh_token_sealed = $4, scope = $5, token_type = $6, time_stamp = $7,
+                key_id = $8, wrapped_key = $9, access_token = NULL, refresh_token = NULL,
+                sealed_with_tenant = TRUE, last_refresh_error = NULL, reauthorization_required = FALSE
+            WHERE tenant_id = $10 AND scope = $5
         "#;
 
-        let access_token = token.access_token.expose_secret();
-        let refresh_token = token.refresh_token.as_ref().map(|rt| rt.expose_secret());
+        let sealed = self.seal(tenant_id, token)?;
 
         let mut conn = self.pool.acquire().await?;
         let res = sqlx::query(query)
-            .bind(access_token)
+            .bind(sealed.access_token)
             .bind(&token.api_domain)
             .bind(token.expires_in)
-            .bind(refresh_token)
+            .bind(sealed.refresh_token)
             .bind(&token.scope)
             .bind(&token.token_type)
             .bind(token.time_stamp)
+            .bind(sealed.key_id)
+            .bind(sealed.wrapped_key)
+            .bind(tenant_id)
             .execute(&mut *conn)
             .await
             .map_err(Error::from)?;
@@ -126,4 +217,196 @@ impl<'a> Tokens<'a> {
 
         Ok(())
     }
+
+    /// Encrypts plaintext rows and re-wraps rows sealed with a retired key or
+    /// without their tenant, so that every row ends up under the active key
+    /// and bound to its tenant. Returns the number of rows rewritten.
+    pub async fn reencrypt_all(&self) -> Result<usize> {
+        let query = format!(
+            "SELECT {TOKEN_COLUMNS} FROM tokens \
+            WHERE key_id IS NULL OR key_id <> $1 OR NOT sealed_with_tenant"
+        );
+
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query_as::<_, TokenRow>(&query)
+            .bind(self.cipher.active_key_id())
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        let count = rows.len();
+        for row in rows {
+            let tenant_id = row.tenant_id;
+            let token = self.open(row)?;
+            self.update(tenant_id, &token).await?;
+            tracing::info!(tenant = %tenant_id, scope = %token.scope, "token re-encrypted");
+        }
+
+        Ok(count)
+    }
+
+    fn seal(&self, tenant_id: Uuid, token: &Token) -> Result<SealedToken> {
+        let data_key = self.cipher.new_data_key()?;
+        let aad = aad(tenant_id, &token.scope);
+
+        Ok(SealedToken {
+            access_token: data_key.seal(&token.access_token, &aad)?,
+            refresh_token: token
+                .refresh_token
+                .as_ref()
+                .map(|rt| data_key.seal(rt, &aad))
+                .transpose()?,
+            key_id: data_key.key_id,
+            wrapped_key: data_key.wrapped,
+        })
+    }
+
+    fn open(&self, row: TokenRow) -> Result<Token> {
+        let (access_token, refresh_token) = match (row.key_id, row.wrapped_key) {
+            (Some(key_id), Some(wrapped_key)) => {
+                let data_key = self.cipher.unwrap_data_key(&key_id, &wrapped_key)?;
+                let aad = if row.sealed_with_tenant {
+                    aad(row.tenant_id, &row.scope)
+                } else {
+                    row.scope.clone()
+                };
+                let access_token = row
+                    .access_token_sealed
+                    .ok_or(Error::custom("Sealed token is missing its access token"))?;
+
+                (
+                    data_key.open(&access_token, &aad)?,
+                    row.refresh_token_sealed
+                        .map(|rt| data_key.open(&rt, &aad))
+                        .transpose()?,
+                )
+            }
+            _ => (
+                Secret::new(
+                    row.access_token
+                        .ok_or(Error::custom("Token is missing its access token"))?,
+                ),
+                row.refresh_token.map(Secret::new),
+            ),
+        };
+
+        Ok(Token {
+            access_token,
+            api_domain: row.api_domain,
+            expires_in: row.expires_in,
+            refresh_token,
+            scope: row.scope,
+            token_type: row.token_type,
+            time_stamp: row.time_stamp,
+        })
+    }
+
+    pub async fn record_refresh_success(&self, tenant_id: Uuid, scope: &str) -> Result<()> {
+        let query = r#"
+            UPDATE tokens
+            SET last_refresh_attempt_at = now(), last_refreshed_at = now(),
+                last_refresh_error = NULL, reauthorization_required = FALSE
+            WHERE tenant_id = $1 AND scope = $2
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(tenant_id)
+            .bind(scope)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    pub async fn record_refresh_failure(
+        &self,
+        tenant_id: Uuid,
+        scope: &str,
+        error: &str,
+        reauthorization_required: bool,
+    ) -> Result<()> {
+        let query = r#"
+            UPDATE tokens
+            SET last_refresh_attempt_at = now(), last_refresh_error = $3,
+                reauthorization_required = $4
+            WHERE tenant_id = $1 AND scope = $2
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(tenant_id)
+            .bind(scope)
+            .bind(error)
+            .bind(reauthorization_required)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    pub async fn get_health(&self, tenant_id: Uuid) -> Result<Vec<TokenHealth>> {
+        let query = r#"
+            SELECT tenant_id, scope, api_domain, time_stamp + make_interval(secs => expires_in) AS expires_at,
+                   last_refresh_attempt_at, last_refreshed_at, last_refresh_error,
+                   reauthorization_required
+            FROM tokens
+            WHERE tenant_id = $1
+            ORDER BY scope
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let res = sqlx::query_as::<_, TokenHealth>(query)
+            .bind(tenant_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(res.into_iter().map(TokenHealth::with_status).collect())
+    }
+
+    /// The health of every tenant's tokens, for the background refresher.
+    pub async fn get_health_all(&self) -> Result<Vec<TokenHealth>> {
+        let query = r#"
+            SELECT tenant_id, scope, api_domain, time_stamp + make_interval(secs => expires_in) AS expires_at,
+                   last_refresh_attempt_at, last_refreshed_at, last_refresh_error,
+                   reauthorization_required
+            FROM tokens
+            ORDER BY tenant_id, scope
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let res = sqlx::query_as::<_, TokenHealth>(query)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(res.into_iter().map(TokenHealth::with_status).collect())
+    }
+
+    pub async fn get_health_by_scope(
+        &self,
+        tenant_id: Uuid,
+        scope: &str,
+    ) -> Result<Option<TokenHealth>> {
+        let query = r#"
+            SELECT tenant_id, scope, api_domain, time_stamp + make_interval(secs => expires_in) AS expires_at,
+                   last_refresh_attempt_at, last_refreshed_at, last_refresh_error,
+                   reauthorization_required
+            FROM tokens
+            WHERE tenant_id = $1 AND scope = $2
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let res = sqlx::query_as::<_, TokenHealth>(query)
+            .bind(tenant_id)
+            .bind(scope)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(res.map(TokenHealth::with_status))
+    }
 }
@@ -0,0 +1,237 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{Role, User};
+use crate::error::{Error, Result};
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: Uuid,
+    tenant_id: Uuid,
+    email: String,
+    role: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = Error;
+
+    fn try_from(row: UserRow) -> Result<Self> {
+        Ok(User {
+            id: row.id,
+            tenant_id: row.tenant_id,
+            email: row.email,
+            role: Role::try_from(row.role)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+pub struct Users<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> Users<'a> {
+    pub async fn insert(
+        &self,
+        tenant_id: Uuid,
+        email: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> Result<User> {
+        let query = r#"
+            INSERT INTO users (id, tenant_id, email, password_hash, role)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, tenant_id, email, role, created_at
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, UserRow>(query)
+            .bind(Uuid::new_v4())
+            .bind(tenant_id)
+            .bind(email.trim().to_lowercase())
+            .bind(password_hash)
+            .bind(role.as_str())
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.try_into()
+    }
+
+    pub async fn get_all(&self, tenant_id: Uuid) -> Result<Vec<User>> {
+        let query = r#"
+            SELECT id, tenant_id, email, role, created_at
+            FROM users
+            WHERE tenant_id = $1
+            ORDER BY email
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query_as::<_, UserRow>(query)
+            .bind(tenant_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        rows.into_iter().map(User::try_from).collect()
+    }
+
+    pub async fn get_by_id(&self, id: Uuid) -> Result<Option<User>> {
+        let query = "SELECT id, tenant_id, email, role, created_at FROM users WHERE id = $1";
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, UserRow>(query)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(User::try_from).transpose()
+    }
+
+    /// The user and their password hash, for checking a login.
+    pub async fn get_credentials(&self, email: &str) -> Result<Option<(User, String)>> {
+        let query = r#"
+            SELECT id, tenant_id, email, role, created_at, password_hash
+            FROM users
+            WHERE email = $1
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, (Uuid, Uuid, String, String, DateTime<Utc>, String)>(query)
+            .bind(email.trim().to_lowercase())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(|(id, tenant_id, email, role, created_at, password_hash)| {
+            let user = User::try_from(UserRow {
+                id,
+                tenant_id,
+                email,
+                role,
+                created_at,
+            })?;
+            Ok((user, password_hash))
+        })
+        .transpose()
+    }
+}
+
+pub struct Sessions<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> Sessions<'a> {
+    pub async fn insert(
+        &self,
+        token_hash: &str,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let query = r#"
+            INSERT INTO sessions (token_hash, user_id, expires_at)
+            VALUES ($1, $2, $3)
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(token_hash)
+            .bind(user_id)
+            .bind(expires_at)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    pub async fn get_user(&self, token_hash: &str) -> Result<Option<User>> {
+        let query = r#"
+            SELECT users.id, users.tenant_id, users.email, users.role, users.created_at
+            FROM sessions
+            JOIN users ON users.id = sessions.user_id
+            WHERE sessions.token_hash = $1 AND sessions.expires_at > now()
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, UserRow>(query)
+            .bind(token_hash)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(User::try_from).transpose()
+    }
+
+    pub async fn delete(&self, token_hash: &str) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+            .bind(token_hash)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= now()")
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct ApiKeys<'a> {
+    pub pool: &'a PgPool,
+}
+
+impl<'a> ApiKeys<'a> {
+    pub async fn insert(&self, key_hash: &str, user_id: Uuid, name: &str) -> Result<()> {
+        let query = r#"
+            INSERT INTO api_keys (key_hash, user_id, name)
+            VALUES ($1, $2, $3)
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query(query)
+            .bind(key_hash)
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(())
+    }
+
+    /// The owner of the key, also recording that the key has been used.
+    pub async fn get_user(&self, key_hash: &str) -> Result<Option<User>> {
+        let query = r#"
+            WITH used AS (
+                UPDATE api_keys
+                SET last_used_at = now()
+                WHERE key_hash = $1
+                RETURNING user_id
+            )
+            SELECT users.id, users.tenant_id, users.email, users.role, users.created_at
+            FROM used
+            JOIN users ON users.id = used.user_id
+        "#;
+
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query_as::<_, UserRow>(query)
+            .bind(key_hash)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(Error::from)?;
+
+        row.map(User::try_from).transpose()
+    }
+}
@@ -1,5 +1,11 @@
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse};
 use derive_more::From;
+use serde_json::{json, Value};
+
+use crate::routes::request_id;
+use crate::zoho::ErrorKind;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -8,6 +14,26 @@ pub enum Error {
     #[from]
     Custom(String),
 
+    /// The request carries no valid session or API key.
+    Unauthorized,
+
+    /// The user's role does not allow the request.
+    Forbidden,
+
+    /// The request itself is malformed, such as a missing query parameter.
+    BadRequest(String),
+
+    NotFound(String),
+
+    /// The request clashes with existing data, such as a taken email.
+    Conflict(String),
+
+    /// No Zoho token has been stored yet, an admin has to connect Zoho.
+    ZohoNotConnected,
+
+    /// The organization is not one of the caller's tenant.
+    UnknownOrganization(String),
+
     #[from]
     Zoho(crate::zoho::Error),
 
@@ -37,6 +63,104 @@ impl Error {
     pub fn custom(val: impl std::fmt::Display) -> Self {
         Self::Custom(val.to_string())
     }
+
+    /// The status, a stable machine readable code and a message safe to show to users.
+    fn classify(&self) -> (StatusCode, &'static str, String) {
+        match self {
+            Self::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "Sign in to continue".to_string(),
+            ),
+            Self::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                "Your role does not allow this".to_string(),
+            ),
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg.clone()),
+            Self::Chrono(err) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_date",
+                format!("Dates must look like 2024-05-27: {err}"),
+            ),
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
+            Self::Sqlx(sqlx::Error::Database(err)) if err.is_unique_violation() => (
+                StatusCode::CONFLICT,
+                "conflict",
+                "A record with the same value already exists".to_string(),
+            ),
+            Self::ZohoNotConnected => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "zoho_not_connected",
+                "Zoho Books has not been connected yet".to_string(),
+            ),
+            Self::UnknownOrganization(organization_id) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_organization",
+                format!("Organization {organization_id} is not connected"),
+            ),
+            Self::Zoho(err) => zoho_status(err),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "Internal Server Error".to_string(),
+            ),
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            Self::Zoho(crate::zoho::Error::Response { code, message, .. }) => Some(json!({
+                "zoho_code": code,
+                "zoho_message": message,
+            })),
+            Self::Zoho(crate::zoho::Error::Parse { path, .. }) => Some(json!({ "path": path })),
+            _ => None,
+        }
+    }
+}
+
+fn zoho_status(err: &crate::zoho::Error) -> (StatusCode, &'static str, String) {
+    let (status, code, msg) = match err.kind() {
+        ErrorKind::NotFound => (
+            StatusCode::NOT_FOUND,
+            "not_found",
+            "Zoho Books has no such record",
+        ),
+        ErrorKind::InvalidRequest => (
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+            "Zoho Books rejected the request parameters",
+        ),
+        ErrorKind::InvalidOrganization => (
+            StatusCode::BAD_REQUEST,
+            "invalid_organization",
+            "The organization does not exist or is not connected",
+        ),
+        ErrorKind::ReauthorizationRequired => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "zoho_reauthorization_required",
+            "Zoho Books access was revoked, an admin has to connect Zoho again",
+        ),
+        ErrorKind::RateLimited => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "zoho_rate_limited",
+            "Zoho Books is rate limiting requests, try again shortly",
+        ),
+        ErrorKind::Unavailable => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "zoho_unavailable",
+            "Zoho Books could not be reached",
+        ),
+        ErrorKind::Unauthorized | ErrorKind::Upstream => (
+            StatusCode::BAD_GATEWAY,
+            "zoho_error",
+            "Zoho Books returned an error",
+        ),
+    };
+
+    (status, code, msg.to_string())
 }
 
 impl From<&str> for Error {
@@ -45,12 +169,45 @@ impl From<&str> for Error {
     }
 }
 
+impl From<QueryRejection> for Error {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for Error {
+    fn from(rejection: PathRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        tracing::error!("{self:?}");
-        tracing::error!("<-- 500");
-
-        (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+        let (status, code, message) = self.classify();
+
+        if status.is_server_error() {
+            tracing::error!("{self:?}");
+            tracing::error!("<-- {}", status.as_u16());
+        } else {
+            tracing::warn!("<-- {} {code}: {message}", status.as_u16());
+        }
+
+        let mut body = json!({
+            "code": code,
+            "message": message,
+            "request_id": request_id::current(),
+        });
+        if let Some(details) = self.details() {
+            body["details"] = details;
+        }
+
+        (status, Json(body)).into_response()
     }
 }
 
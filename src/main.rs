@@ -1,5 +1,7 @@
+use delivr::auth::Role;
 use delivr::config::get_config;
 use delivr::{app, config::Environment};
+use secrecy::Secret;
 use tracing::subscriber::set_global_default;
 use tracing_log::LogTracer;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
@@ -37,6 +39,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     tracing::info!("{:#?}", config);
+
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("reencrypt-tokens") => {
+            let count = app::reencrypt_tokens(&config).await?;
+            tracing::info!("re-encrypted {count} tokens");
+            return Ok(());
+        }
+        Some("create-tenant") => {
+            let Some(name) = args.get(2) else {
+                return Err("usage: delivr create-tenant <name>".into());
+            };
+
+            let tenant = app::create_tenant(&config, name).await?;
+            tracing::info!("created tenant {} {}", tenant.name, tenant.id);
+            return Ok(());
+        }
+        // delivr create-user <email> <role> [tenant], reading the password from stdin
+        Some("create-user") => {
+            let (Some(email), Some(role)) = (args.get(2), args.get(3)) else {
+                return Err("usage: delivr create-user <email> <role> [tenant]".into());
+            };
+            let tenant = args.get(4).map(String::as_str);
+            let role = Role::try_from(role.clone())?;
+
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+            let password = Secret::new(password.trim_end_matches(['\r', '\n']).to_string());
+
+            let user = app::create_user(&config, tenant, email, password, role).await?;
+            tracing::info!("created {} {}", user.role.as_str(), user.email);
+            return Ok(());
+        }
+        _ => {}
+    }
+
     app::serve(&config).await?;
 
     Ok(())
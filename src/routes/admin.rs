@@ -0,0 +1,53 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::ExposeSecret;
+use tracing::instrument;
+
+use crate::app::AppState;
+use crate::auth::{roles, Require};
+use crate::database::Tokens;
+use crate::error::Result;
+
+#[derive(serde::Serialize)]
+struct ExportedToken {
+    scope: String,
+    api_domain: String,
+    access_token: String,
+    refresh_token: Option<String>,
+    token_type: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The only endpoint that ever returns raw token secrets, those of the admin's tenant.
+#[instrument(skip(state, admin))]
+pub async fn export_tokens(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::warn!(user = %admin.user.email, "--> exporting token secrets");
+
+    let tokens = Tokens {
+        pool: &state.pool,
+        cipher: &state.cipher,
+    };
+    let exported: Vec<ExportedToken> = tokens
+        .get_all(admin.user.tenant_id)
+        .await?
+        .into_iter()
+        .map(|token| ExportedToken {
+            expires_at: token.expires_at(),
+            access_token: token.access_token.expose_secret().clone(),
+            refresh_token: token
+                .refresh_token
+                .as_ref()
+                .map(|rt| rt.expose_secret().clone()),
+            scope: token.scope,
+            api_domain: token.api_domain,
+            token_type: token.token_type,
+        })
+        .collect();
+
+    tracing::info!("<-- 200");
+    Ok(Json(exported))
+}
@@ -0,0 +1,81 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use secrecy::Secret;
+use tracing::instrument;
+
+use crate::app::AppState;
+use crate::auth::{
+    generate_secret, hash_secret, verify_password, User, SESSION_COOKIE, SESSION_TTL_DAYS,
+};
+use crate::config::Environment;
+use crate::database::{Sessions, Users};
+use crate::error::{Error, Result};
+use crate::routes::extract::JsonBody;
+
+#[derive(serde::Deserialize)]
+pub struct Credentials {
+    email: String,
+    password: Secret<String>,
+}
+
+#[instrument(skip(state, jar, credentials), fields(email = %credentials.email))]
+pub async fn login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    JsonBody(credentials): JsonBody<Credentials>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let users = Users { pool: &state.pool };
+    let Some((user, password_hash)) = users.get_credentials(&credentials.email).await? else {
+        tracing::warn!("unknown user");
+        return Err(Error::Unauthorized);
+    };
+
+    if !verify_password(credentials.password, password_hash).await? {
+        tracing::warn!("wrong password");
+        return Err(Error::Unauthorized);
+    }
+
+    let sessions = Sessions { pool: &state.pool };
+    sessions.delete_expired().await?;
+
+    let token = generate_secret();
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(SESSION_TTL_DAYS);
+    sessions
+        .insert(&hash_secret(&token), user.id, expires_at)
+        .await?;
+
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .secure(state.config.environment == Environment::Production)
+        .same_site(SameSite::Lax);
+
+    tracing::info!("<-- 200");
+    Ok((jar.add(cookie), Json(user)))
+}
+
+#[instrument(skip(state, jar))]
+pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    if let Some(session) = jar.get(SESSION_COOKIE) {
+        let sessions = Sessions { pool: &state.pool };
+        sessions.delete(&hash_secret(session.value())).await?;
+    }
+
+    tracing::info!("<-- 204");
+    Ok((
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+#[instrument(skip(user))]
+pub async fn me(user: User) -> Json<User> {
+    Json(user)
+}
@@ -0,0 +1,49 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use tracing::instrument;
+
+use crate::app::AppState;
+use crate::auth::{roles, Require};
+use crate::error::Result;
+use crate::routes::extract::Path;
+use crate::zoho::InvoiceId;
+
+/// Drops everything cached for an organization, e.g. after bulk edits in Zoho.
+#[instrument(skip(state, manager))]
+pub async fn invalidate_organization(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+    Path(organization_id): Path<String>,
+) -> Result<StatusCode> {
+    tracing::info!("-->");
+
+    state
+        .organizations
+        .authorize(manager.user.tenant_id, &organization_id)
+        .await?;
+    state.cache.invalidate_organization(&organization_id);
+
+    tracing::info!("<-- 204");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Drops a single invoice, and the lists it may appear in, from the cache.
+#[instrument(skip(state, manager))]
+pub async fn invalidate_invoice(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+    Path((organization_id, invoice_id)): Path<(String, InvoiceId)>,
+) -> Result<StatusCode> {
+    tracing::info!("-->");
+
+    state
+        .organizations
+        .authorize(manager.user.tenant_id, &organization_id)
+        .await?;
+    state
+        .cache
+        .invalidate_invoice(&organization_id, &invoice_id);
+
+    tracing::info!("<-- 204");
+    Ok(StatusCode::NO_CONTENT)
+}
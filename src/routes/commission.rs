@@ -0,0 +1,45 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use tracing::instrument;
+
+use crate::app::{AppState, CommissionPlan};
+use crate::auth::{roles, Require};
+use crate::database::CommissionPlans;
+use crate::error::{Error, Result};
+use crate::routes::extract::JsonBody;
+
+#[instrument(skip(state, manager))]
+pub async fn get_plan(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let plans = CommissionPlans { pool: &state.pool };
+    let plan = plans
+        .get(manager.user.tenant_id)
+        .await?
+        .ok_or(Error::NotFound("No commission plan is set up".to_string()))?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(plan))
+}
+
+/// Replaces the tenant's commission plan; statements are always worked out
+/// with the current plan.
+#[instrument(skip(state, admin))]
+pub async fn update_plan(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    JsonBody(plan): JsonBody<CommissionPlan>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    plan.validate()?;
+    let plans = CommissionPlans { pool: &state.pool };
+    plans.save(admin.user.tenant_id, &plan).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(plan))
+}
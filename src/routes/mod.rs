@@ -1,34 +1,92 @@
-use axum::extract::{Path, Query as QueryExtractor, State};
+mod admin;
+mod auth;
+mod cache;
+mod commission;
+pub(crate) mod extract;
+mod oauth;
+mod reports;
+pub mod request_id;
+mod sync;
+mod tenants;
+mod users;
+
+use axum::extract::State;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderValue, Method};
+use axum::routing::{delete, get, post};
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse};
-use axum::{routing::get, Router};
-use tower_http::cors::{Any, CorsLayer};
+use axum::{middleware, Router};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::services::ServeDir;
 use tower_http::trace::{self, TraceLayer};
 use tracing::instrument;
+use uuid::Uuid;
 
 use crate::app::AppState;
-use crate::config::Config;
+use crate::auth::{roles, Require, User};
 use crate::database::Tokens;
 use crate::error::{Error, Result};
-use crate::zoho::{Invoice, InvoiceIDs, Query};
-
-pub async fn build_router(config: &Config) -> Result<Router> {
-    let state = AppState::build_state(config).await?;
-
+use crate::zoho::{
+    CustomerId, Invoice, InvoiceId, Query, SalespersonId, SortColumn, SortOrder, StatusFilter,
+    Summary,
+};
+use extract::{Path, Query as QueryExtractor};
+
+pub fn build_router(state: AppState) -> Router {
+    // the dashboard is served from this app; other origins only get in if they
+    // are the configured base url, and then with credentials
+    let origin = HeaderValue::from_str(&state.config.application.base_url)
+        .map(AllowOrigin::exact)
+        .unwrap_or_else(|_| AllowOrigin::list([]));
     let cors = CorsLayer::new()
-        .allow_origin(Any) // Allow all origins
-        .allow_methods(Any) // Allow all methods
-        .allow_headers(Any); // Allow all headers
+        .allow_origin(origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE, request_id::REQUEST_ID_HEADER])
+        .expose_headers([request_id::REQUEST_ID_HEADER])
+        .allow_credentials(true);
 
     let serve_website = ServeDir::new("static");
 
-    Ok(Router::new()
+    Router::new()
         .route("/health", get(health))
-        .route("/token/:code", get(request_token))
+        .route("/auth/login", post(auth::login))
+        .route("/auth/logout", post(auth::logout))
+        .route("/auth/me", get(auth::me))
+        .route("/users", get(users::list_users).post(users::create_user))
+        .route("/users/:id/api-keys", post(users::create_api_key))
+        .route("/oauth/authorize", get(oauth::authorize))
+        .route("/oauth/callback", get(oauth::callback))
+        .route(
+            "/tenant",
+            get(tenants::get_tenant).put(tenants::update_tenant),
+        )
+        .route("/organizations", get(tenants::list_organizations))
         .route("/tokens", get(get_all_tokens))
         .route("/tokens/:scope", get(get_token))
+        .route("/status/tokens", get(get_all_tokens))
+        .route("/status/sync", get(sync::sync_status))
+        .route("/sync/:organization_id", post(sync::sync_organization))
+        .route(
+            "/cache/:organization_id",
+            delete(cache::invalidate_organization),
+        )
+        .route(
+            "/cache/:organization_id/invoices/:invoice_id",
+            delete(cache::invalidate_invoice),
+        )
+        .route("/admin/tokens/export", get(admin::export_tokens))
         .route("/invoices", get(invoices_by_date))
+        .route("/invoices/summary", get(invoice_summary))
+        .route("/reports/sales", get(reports::sales_report))
+        .route("/reports/salespeople", get(reports::salesperson_report))
+        .route("/reports/commission", get(reports::commission_report))
+        .route(
+            "/commission/plan",
+            get(commission::get_plan).put(commission::update_plan),
+        )
         .route("/invoice/:id", get(invoice))
         .nest_service("/", serve_website)
         // Add a tracing layer to all requests
@@ -38,8 +96,9 @@ pub async fn build_router(config: &Config) -> Result<Router> {
                 .on_request(trace::DefaultOnRequest::new())
                 .on_response(trace::DefaultOnResponse::new()),
         )
+        .layer(middleware::from_fn(request_id::middleware))
         .layer(cors)
-        .with_state(state))
+        .with_state(state)
 }
 
 #[instrument(skip(state))]
@@ -56,163 +115,289 @@ pub async fn health(State(state): State<AppState>) -> Result<impl IntoResponse>
     }
 }
 
-#[instrument(skip(state, code))]
-pub async fn request_token(
+/// Token metadata only; secrets are never exposed here.
+#[instrument(skip(manager, state))]
+pub async fn get_token(
+    manager: Require<roles::Manager>,
     State(state): State<AppState>,
-    Path(code): Path<String>,
+    Path(scope): Path<String>,
 ) -> Result<impl IntoResponse> {
     tracing::info!("-->");
 
-    if code.is_empty() {
-        return Err(Error::custom("Missing code"));
-    }
-
-    let client = &state.client;
-    let tokens = Tokens { pool: &state.pool };
-
-    let token = client.request_token(&code).await?;
-    if tokens.contains_scope(&token.scope).await? {
-        tracing::warn!("token already exists, replacing the existing token");
-        tokens.update(&token).await?;
-    } else {
-        tokens.insert(&token).await?;
-    }
+    let tokens = Tokens {
+        pool: &state.pool,
+        cipher: &state.cipher,
+    };
+    let token = tokens
+        .get_health_by_scope(manager.user.tenant_id, &scope)
+        .await?
+        .ok_or(Error::NotFound(format!("No token for scope {scope}")))?;
 
     tracing::info!("<-- 200");
-    Ok(StatusCode::OK)
+    Ok(Json(token))
 }
 
-#[instrument(skip(state))]
-pub async fn get_token(
+/// Token metadata only; secrets are never exposed here.
+#[instrument(skip(manager, state))]
+pub async fn get_all_tokens(
+    manager: Require<roles::Manager>,
     State(state): State<AppState>,
-    Path(scope): Path<String>,
 ) -> Result<impl IntoResponse> {
     tracing::info!("-->");
 
-    let tokens = Tokens { pool: &state.pool };
-    let token = tokens.get_by_scope(&scope).await?;
+    let tokens = Tokens {
+        pool: &state.pool,
+        cipher: &state.cipher,
+    };
+    let health = tokens.get_health(manager.user.tenant_id).await?;
 
     tracing::info!("<-- 200");
-    Ok(Json(token))
-}
-
-#[instrument(skip(state))]
-pub async fn get_all_tokens(State(state): State<AppState>) -> Result<impl IntoResponse> {
-    tracing::info!("get all tokens");
-
-    let tokens = Tokens { pool: &state.pool };
-    let tokens = tokens.get_all().await?;
-
-    tracing::info!("{:#?}", tokens);
-
-    Ok(Json(tokens))
+    Ok(Json(health))
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
 struct InvoiceQuery {
-    organization_id: String,
-    date: String,
+    /// Defaults to the tenant's default organization.
+    organization_id: Option<String>,
+    /// A business date like `2024-05-27`. Without any date filter the
+    /// invoices of today, in the business timezone, are returned.
+    date: Option<String>,
+    date_start: Option<String>,
+    date_end: Option<String>,
+    status: Option<StatusFilter>,
+    customer_id: Option<CustomerId>,
+    salesperson_id: Option<SalespersonId>,
+    last_modified_time: Option<DateTime<Utc>>,
+    search_text: Option<String>,
+    sort_column: Option<SortColumn>,
+    sort_order: Option<SortOrder>,
+}
+
+impl InvoiceQuery {
+    fn to_query<'a>(
+        &'a self,
+        today: NaiveDate,
+        default_organization_id: Option<&'a str>,
+    ) -> Result<Query<'a>> {
+        let mut builder = Query::builder().default_organization_id(default_organization_id);
+        if let Some(organization_id) = &self.organization_id {
+            builder = builder.organization_id(organization_id);
+        }
+        if let Some(date) = &self.date {
+            builder = builder.date(date)?;
+        }
+        if let Some(date_start) = &self.date_start {
+            builder = builder.date_start(date_start)?;
+        }
+        if let Some(date_end) = &self.date_end {
+            builder = builder.date_end(date_end)?;
+        }
+        if self.date.is_none() && self.date_start.is_none() && self.date_end.is_none() {
+            builder = builder.day(today);
+        }
+        if let Some(status) = self.status {
+            builder = builder.status(status);
+        }
+        if let Some(customer_id) = &self.customer_id {
+            builder = builder.customer_id(customer_id);
+        }
+        if let Some(salesperson_id) = &self.salesperson_id {
+            builder = builder.salesperson_id(salesperson_id);
+        }
+        if let Some(since) = self.last_modified_time {
+            builder = builder.last_modified_time(since);
+        }
+        if let Some(text) = &self.search_text {
+            builder = builder.search_text(text);
+        }
+        match (self.sort_column, self.sort_order) {
+            (Some(column), order) => {
+                builder = builder.sort(column, order.unwrap_or(SortOrder::Ascending));
+            }
+            (None, Some(_)) => {
+                return Err(Error::BadRequest(
+                    "sort_order needs a sort_column".to_string(),
+                ));
+            }
+            (None, None) => {}
+        }
+
+        builder.build()
+    }
 }
 
 #[instrument(
-    skip(state, query)
+    skip(driver, state, query)
     fields(
-        organization = %query.organization_id,
-        date = %query.date
+        organization = ?query.organization_id,
+        date = ?query.date,
+        date_start = ?query.date_start,
+        date_end = ?query.date_end,
     ))]
 pub async fn invoices_by_date(
+    driver: Require<roles::Driver>,
     State(state): State<AppState>,
-    query: QueryExtractor<InvoiceQuery>,
+    QueryExtractor(query): QueryExtractor<InvoiceQuery>,
 ) -> Result<impl IntoResponse> {
     tracing::info!("-->");
 
-    let query = query.clone();
+    let invoices = matching_invoices(&state, driver.user.tenant_id, &query).await?;
+    // the headline figures are those of the summary, so they always agree
+    let summary = Summary::of(&invoices);
+    let body = serde_json::json!({
+        "invoices": invoices,
+        "sales": summary.net_sales,
+        "profit": summary.profit,
+        "summary": summary,
+    });
 
-    let query = Query::builder()
-        .organization_id(&query.organization_id)
-        .date(&query.date)?
-        .build()?;
+    tracing::info!("<-- 200");
 
-    let tokens = Tokens { pool: &state.pool };
-    let mut token = tokens
-        .get_by_scope("ZohoBooks.fullaccess.all")
-        .await?
-        .ok_or(Error::custom("No token found"))?;
+    Ok(Json(visible_to(&driver.user, body)))
+}
 
-    let client = &state.client;
+/// The figures of `/invoices` without the invoices themselves.
+#[instrument(
+    skip(driver, state, query)
+    fields(
+        organization = ?query.organization_id,
+        date = ?query.date,
+        date_start = ?query.date_start,
+        date_end = ?query.date_end,
+    ))]
+pub async fn invoice_summary(
+    driver: Require<roles::Driver>,
+    State(state): State<AppState>,
+    QueryExtractor(query): QueryExtractor<InvoiceQuery>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
 
-    if token.is_expired() {
-        tracing::info!("Token is expired, refreshing token...");
+    let invoices = matching_invoices(&state, driver.user.tenant_id, &query).await?;
+    let summary = serde_json::to_value(Summary::of(&invoices))?;
 
-        token = client.refresh_token(&token).await.map_err(Error::from)?;
+    tracing::info!("<-- 200");
 
-        tokens.update(&token).await?;
+    Ok(Json(visible_to(&driver.user, summary)))
+}
 
-        tracing::info!("Token has been refreshed");
-    }
+/// The tenant's invoices matching the query, of its default organization unless
+/// the query names one.
+async fn matching_invoices(
+    state: &AppState,
+    tenant_id: Uuid,
+    query: &InvoiceQuery,
+) -> Result<Vec<Invoice>> {
+    let default_organization_id =
+        default_organization(state, tenant_id, &query.organization_id).await?;
+    let today = state.today(tenant_id).await?;
+    let query = query.to_query(today, default_organization_id.as_deref())?;
+    state
+        .organizations
+        .authorize(tenant_id, query.organization_id)
+        .await?;
+
+    state.mirror.invoices(tenant_id, today, &query).await
+}
 
-    let value = client.get_invoices_with_query(&token, &query).await?;
-    let ids = InvoiceIDs::from(value);
-    tracing::info!("<-- {} invoices", ids.inner.len());
-
-    let mut invoices = vec![];
-    for invoice in &ids.inner {
-        let value = client
-            .get_invoice(&token, &invoice.id.clone(), &query)
-            .await?;
-        let invoice = Invoice::from(value);
-        invoices.push(invoice);
+/// The tenant's default organization, looked up only when the request names none.
+async fn default_organization(
+    state: &AppState,
+    tenant_id: Uuid,
+    organization_id: &Option<String>,
+) -> Result<Option<String>> {
+    match organization_id {
+        Some(_) => Ok(None),
+        None => state.organizations.default_for(tenant_id).await,
     }
-
-    invoices.sort_by(|a, b| a.created_time.cmp(&b.created_time));
-
-    tracing::info!("<-- 200");
-
-    Ok(Json(invoices))
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
 struct OrgaznizationQuery {
-    organization_id: String,
+    organization_id: Option<String>,
 }
 
 #[instrument(
     name = "invoice"
-    skip(state, id, query)
+    skip(driver, state, id, query)
     fields(
-        organization = %query.organization_id,
+        organization = ?query.organization_id,
         id = %id
     ))]
 pub async fn invoice(
+    driver: Require<roles::Driver>,
     State(state): State<AppState>,
-    Path(id): Path<String>,
+    Path(id): Path<InvoiceId>,
     QueryExtractor(query): QueryExtractor<OrgaznizationQuery>,
 ) -> Result<impl IntoResponse> {
     tracing::info!("-->");
-    let tokens = Tokens { pool: &state.pool };
-    let mut token = tokens
-        .get_by_scope("ZohoBooks.fullaccess.all")
-        .await?
-        .ok_or(Error::custom("No token found"))?;
 
-    let client = &state.client;
-    if token.is_expired() {
-        tracing::info!("Token is expired, refreshing token...");
+    let tenant_id = driver.user.tenant_id;
+    let default_organization_id =
+        default_organization(&state, tenant_id, &query.organization_id).await?;
+    let mut builder = Query::builder().default_organization_id(default_organization_id.as_deref());
+    if let Some(organization_id) = &query.organization_id {
+        builder = builder.organization_id(organization_id);
+    }
+    let query = builder.build()?;
+    state
+        .organizations
+        .authorize(tenant_id, query.organization_id)
+        .await?;
+
+    let today = state.today(tenant_id).await?;
+    let invoice = state.mirror.invoice(tenant_id, today, &id, &query).await?;
+    let value = visible_to(&driver.user, serde_json::to_value(invoice)?);
 
-        token = client.refresh_token(&token).await.map_err(Error::from)?;
+    tracing::info!("<-- 200");
+    Ok(Json(value))
+}
 
-        tokens.update(&token).await?;
+/// Fields revealing what we paid for goods, hidden from roles that may not see profit.
+const PROFIT_FIELDS: [&str; 5] = [
+    "profit",
+    "item_profit",
+    "purchase_rate",
+    "cost",
+    "margin_percentage",
+];
+
+fn visible_to(user: &User, mut value: Value) -> Value {
+    if !user.role.can_see_profit() {
+        redact(&mut value, &PROFIT_FIELDS);
+    }
+    value
+}
 
-        tracing::info!("Token has been refreshed");
+fn redact(value: &mut Value, fields: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for field in fields {
+                map.remove(*field);
+            }
+            map.values_mut().for_each(|value| redact(value, fields));
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| redact(value, fields)),
+        _ => {}
     }
+}
 
-    let query = Query::builder()
-        .organization_id(&query.organization_id)
-        .build()?;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    let value = client.get_invoice(&token, &id, &query).await?;
-    // tracing::info!("{:#?}", value);
+    #[test]
+    fn redact_removes_nested_fields() {
+        let mut value = serde_json::json!([{
+            "total": 10.0,
+            "profit": 4.0,
+            "line_items": [{ "item_total": 10.0, "item_profit": 4.0, "purchase_rate": 6.0 }]
+        }]);
 
-    tracing::info!("<-- 200");
-    Ok(Json(value))
+        redact(&mut value, &PROFIT_FIELDS);
+
+        assert_eq!(
+            value,
+            serde_json::json!([{ "total": 10.0, "line_items": [{ "item_total": 10.0 }] }])
+        );
+    }
 }
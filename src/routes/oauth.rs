@@ -0,0 +1,204 @@
+use axum::extract::State;
+use axum::response::{IntoResponse, Redirect};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use sha2::Sha256;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::auth::{roles, Require};
+use crate::config::Environment;
+use crate::database::Tokens;
+use crate::error::{Error, Result};
+use crate::routes::extract::Query as QueryExtractor;
+use crate::utils::{from_hex, to_hex};
+
+const STATE_COOKIE: &str = "oauth_state";
+/// How long the user has to get through Zoho's consent screen.
+const STATE_TTL_SECS: i64 = 600;
+
+/// The `state` round-tripped through Zoho: a nonce that must match the one in
+/// the browser's cookie, and when it was issued, signed with the app secret.
+struct OAuthState {
+    nonce: String,
+    issued_at: i64,
+}
+
+impl OAuthState {
+    fn new() -> Self {
+        Self {
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+            issued_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    fn mac(key: &[u8], payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    fn sign(&self, key: &[u8]) -> String {
+        let payload = format!("{}.{}", self.nonce, self.issued_at);
+        let signature = Self::mac(key, &payload).finalize().into_bytes();
+
+        format!("{payload}.{}", to_hex(&signature))
+    }
+
+    fn verify(value: &str, key: &[u8]) -> Result<Self> {
+        let (payload, signature) = value
+            .rsplit_once('.')
+            .ok_or(Error::custom("Malformed state"))?;
+        let signature = from_hex(signature).ok_or(Error::custom("Malformed state"))?;
+
+        Self::mac(key, payload)
+            .verify_slice(&signature)
+            .map_err(|_| Error::custom("State signature mismatch"))?;
+
+        let (nonce, issued_at) = payload
+            .split_once('.')
+            .ok_or(Error::custom("Malformed state"))?;
+        let issued_at: i64 = issued_at
+            .parse()
+            .map_err(|_| Error::custom("Malformed state"))?;
+
+        if chrono::Utc::now().timestamp() - issued_at > STATE_TTL_SECS {
+            return Err(Error::custom("State has expired"));
+        }
+
+        Ok(Self {
+            nonce: nonce.to_string(),
+            issued_at,
+        })
+    }
+}
+
+/// Only admins may connect delivr to Zoho.
+#[instrument(skip(_admin, state, jar))]
+pub async fn authorize(
+    _admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let oauth_state = OAuthState::new();
+    let signed = oauth_state.sign(state.config.application.secret.expose_secret().as_bytes());
+    let url = state.client.authorize_url(&signed)?;
+
+    let cookie = Cookie::build((STATE_COOKIE, oauth_state.nonce))
+        .path("/oauth")
+        .http_only(true)
+        .secure(state.config.environment == Environment::Production)
+        .same_site(SameSite::Lax);
+
+    tracing::info!("<-- 303");
+    Ok((jar.add(cookie), Redirect::to(&url)))
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[instrument(skip(admin, state, jar, query))]
+pub async fn callback(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    jar: CookieJar,
+    QueryExtractor(query): QueryExtractor<CallbackQuery>,
+) -> impl IntoResponse {
+    tracing::info!("-->");
+
+    let nonce = jar
+        .get(STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let jar = jar.remove(Cookie::build(STATE_COOKIE).path("/oauth"));
+
+    let redirect = match connect(&state, admin.user.tenant_id, query, nonce).await {
+        Ok(()) => {
+            tracing::info!("<-- Zoho connected");
+            Redirect::to("/?oauth=success")
+        }
+        Err(err) => {
+            tracing::error!("{err:?}");
+            Redirect::to("/?oauth=error")
+        }
+    };
+
+    (jar, redirect)
+}
+
+/// Stores the token for the admin's tenant and lists the organizations it can access.
+///
+/// Once the token is stored the grant's code is used up, so failing to list
+/// the organizations does not fail the connection: they are listed again
+/// when next needed.
+async fn connect(
+    state: &AppState,
+    tenant_id: Uuid,
+    query: CallbackQuery,
+    nonce: Option<String>,
+) -> Result<()> {
+    if let Some(error) = query.error {
+        return Err(Error::custom(format!("Zoho denied access: {error}")));
+    }
+
+    let signed = query.state.ok_or(Error::custom("Missing state"))?;
+    let oauth_state = OAuthState::verify(
+        &signed,
+        state.config.application.secret.expose_secret().as_bytes(),
+    )?;
+
+    if nonce.as_deref() != Some(oauth_state.nonce.as_str()) {
+        return Err(Error::custom("State does not belong to this browser"));
+    }
+
+    let code = query.code.ok_or(Error::custom("Missing code"))?;
+    let token = state.client.request_token(&code).await?;
+
+    let tokens = Tokens {
+        pool: &state.pool,
+        cipher: &state.cipher,
+    };
+    tokens.save(tenant_id, &token).await?;
+
+    if let Err(err) = state.organizations.refresh(tenant_id).await {
+        tracing::warn!("Failed to list the organizations of the new token: {err:?}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn state_round_trip() -> Result<()> {
+        let state = OAuthState::new();
+        let signed = state.sign(b"key");
+
+        let verified = OAuthState::verify(&signed, b"key")?;
+        assert_eq!(verified.nonce, state.nonce);
+
+        assert!(OAuthState::verify(&signed, b"other key").is_err());
+        assert!(OAuthState::verify(&signed.replace('.', "-"), b"key").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn expired_state_is_rejected() {
+        let state = OAuthState {
+            nonce: "nonce".to_string(),
+            issued_at: chrono::Utc::now().timestamp() - STATE_TTL_SECS - 1,
+        };
+
+        assert!(OAuthState::verify(&state.sign(b"key"), b"key").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::NaiveDate;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::{
+    AppState, Bucket, CommissionReport, SalesReport, SalespersonReport, MAX_REPORT_DAYS,
+};
+use crate::auth::{roles, Require};
+use crate::database::CommissionPlans;
+use crate::error::{Error, Result};
+use crate::routes::default_organization;
+use crate::routes::extract::Query as QueryExtractor;
+use crate::zoho::{Invoice, Query};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ReportQuery {
+    /// Defaults to the tenant's default organization.
+    organization_id: Option<String>,
+    /// Business dates like `2024-05-27`, both inclusive.
+    from: String,
+    to: String,
+    #[serde(default)]
+    bucket: Bucket,
+}
+
+/// A report without buckets, such as a pay period.
+#[derive(serde::Deserialize, Debug)]
+pub struct PeriodQuery {
+    /// Defaults to the tenant's default organization.
+    organization_id: Option<String>,
+    /// Business dates like `2024-05-27`, both inclusive.
+    from: String,
+    to: String,
+}
+
+/// Sales and profit per day, week or month, from the mirror once the
+/// organization is synced and from Zoho until then.
+#[instrument(skip(viewer, state))]
+pub async fn sales_report(
+    viewer: Require<roles::Viewer>,
+    State(state): State<AppState>,
+    QueryExtractor(query): QueryExtractor<ReportQuery>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let (invoices, from, to) = invoices_between(
+        &state,
+        viewer.user.tenant_id,
+        &query.organization_id,
+        &query.from,
+        &query.to,
+    )
+    .await?;
+    let report = SalesReport::of(&invoices, from, to, query.bucket);
+
+    tracing::info!("<-- 200");
+    Ok(Json(report))
+}
+
+#[instrument(skip(viewer, state))]
+pub async fn salesperson_report(
+    viewer: Require<roles::Viewer>,
+    State(state): State<AppState>,
+    QueryExtractor(query): QueryExtractor<PeriodQuery>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let (invoices, from, to) = invoices_between(
+        &state,
+        viewer.user.tenant_id,
+        &query.organization_id,
+        &query.from,
+        &query.to,
+    )
+    .await?;
+    let report = SalespersonReport::of(&invoices, from, to);
+
+    tracing::info!("<-- 200");
+    Ok(Json(report))
+}
+
+/// Every salesperson's commission for the pay period, by the tenant's plan.
+#[instrument(skip(manager, state))]
+pub async fn commission_report(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+    QueryExtractor(query): QueryExtractor<PeriodQuery>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let tenant_id = manager.user.tenant_id;
+    let plans = CommissionPlans { pool: &state.pool };
+    let plan = plans
+        .get(tenant_id)
+        .await?
+        .ok_or(Error::NotFound("No commission plan is set up".to_string()))?;
+
+    let (invoices, from, to) = invoices_between(
+        &state,
+        tenant_id,
+        &query.organization_id,
+        &query.from,
+        &query.to,
+    )
+    .await?;
+    let report = CommissionReport::of(&invoices, from, to, plan);
+
+    tracing::info!("<-- 200");
+    Ok(Json(report))
+}
+
+/// The invoices of an organization of the tenant dated `from` to `to`, with
+/// both dates parsed.
+async fn invoices_between(
+    state: &AppState,
+    tenant_id: Uuid,
+    organization_id: &Option<String>,
+    from: &str,
+    to: &str,
+) -> Result<(Vec<Invoice>, NaiveDate, NaiveDate)> {
+    let default_organization_id = default_organization(state, tenant_id, organization_id).await?;
+    let mut builder = Query::builder()
+        .default_organization_id(default_organization_id.as_deref())
+        .date_start(from)?
+        .date_end(to)?;
+    if let Some(organization_id) = organization_id {
+        builder = builder.organization_id(organization_id);
+    }
+    let query = builder.build()?;
+    let (Some(from), Some(to)) = (query.date_start, query.date_end) else {
+        return Err(Error::BadRequest("from and to are required".to_string()));
+    };
+    if (to - from).num_days() >= MAX_REPORT_DAYS {
+        return Err(Error::BadRequest(format!(
+            "Reports cover at most {MAX_REPORT_DAYS} days"
+        )));
+    }
+
+    state
+        .organizations
+        .authorize(tenant_id, query.organization_id)
+        .await?;
+    let today = state.today(tenant_id).await?;
+    let invoices = state.mirror.invoices(tenant_id, today, &query).await?;
+
+    Ok((invoices, from, to))
+}
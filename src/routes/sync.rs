@@ -0,0 +1,45 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use tracing::instrument;
+
+use crate::app::AppState;
+use crate::auth::{roles, Require};
+use crate::database::SyncCursors;
+use crate::error::Result;
+use crate::routes::extract::Path;
+
+/// Pulls the latest changes of an organization into the mirror right away,
+/// instead of waiting for the background sync.
+#[instrument(skip(state, manager))]
+pub async fn sync_organization(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+    Path(organization_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let tenant_id = manager.user.tenant_id;
+    state
+        .organizations
+        .authorize(tenant_id, &organization_id)
+        .await?;
+    let report = state.mirror.sync(tenant_id, &organization_id).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(report))
+}
+
+#[instrument(skip(state, manager))]
+pub async fn sync_status(
+    manager: Require<roles::Manager>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let cursors = SyncCursors { pool: &state.pool };
+    let cursors = cursors.get_all(manager.user.tenant_id).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(cursors))
+}
@@ -0,0 +1,92 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer};
+use tracing::instrument;
+
+use crate::app::AppState;
+use crate::auth::{roles, Require};
+use crate::database::Tenants;
+use crate::error::Result;
+use crate::routes::extract::JsonBody;
+
+/// The caller's tenant and its settings.
+#[instrument(skip(state, driver))]
+pub async fn get_tenant(
+    driver: Require<roles::Driver>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let tenant = state.tenant(driver.user.tenant_id).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(tenant))
+}
+
+/// Settings left out keep their current value.
+#[derive(serde::Deserialize, Debug)]
+pub struct TenantSettings {
+    /// `null` goes back to `application.timezone`.
+    #[serde(default, deserialize_with = "present")]
+    timezone: Option<Option<Tz>>,
+    /// `null` goes back to the organization discovered from Zoho.
+    #[serde(default, deserialize_with = "present")]
+    default_organization_id: Option<Option<String>>,
+}
+
+/// Tells an explicit `null`, `Some(None)`, from a missing key, `None`.
+fn present<'de, T, D>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[instrument(skip(state, admin))]
+pub async fn update_tenant(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    JsonBody(settings): JsonBody<TenantSettings>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let tenant_id = admin.user.tenant_id;
+    if let Some(Some(organization_id)) = &settings.default_organization_id {
+        state
+            .organizations
+            .authorize(tenant_id, organization_id)
+            .await?;
+    }
+
+    let tenants = Tenants { pool: &state.pool };
+    let tenant = tenants
+        .update_settings(
+            tenant_id,
+            settings.timezone,
+            settings
+                .default_organization_id
+                .as_ref()
+                .map(Option::as_deref),
+        )
+        .await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(tenant))
+}
+
+/// The Zoho organizations the tenant's connection can access.
+#[instrument(skip(state, driver))]
+pub async fn list_organizations(
+    driver: Require<roles::Driver>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let organizations = state.organizations.list(driver.user.tenant_id).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(organizations))
+}
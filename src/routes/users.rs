@@ -0,0 +1,105 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::Secret;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::app::AppState;
+use crate::auth::{generate_secret, hash_password, hash_secret, roles, Require, Role};
+use crate::database::{ApiKeys, Users};
+use crate::error::{Error, Result};
+use crate::routes::extract::{JsonBody, Path};
+
+/// Prefix that makes delivr API keys easy to spot in logs and secret scanners.
+pub const API_KEY_PREFIX: &str = "dlv_";
+
+#[instrument(skip(state, admin))]
+pub async fn list_users(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let users = Users { pool: &state.pool };
+    let users = users.get_all(admin.user.tenant_id).await?;
+
+    tracing::info!("<-- 200");
+    Ok(Json(users))
+}
+
+#[derive(serde::Deserialize)]
+pub struct NewUser {
+    email: String,
+    password: Secret<String>,
+    role: Role,
+}
+
+/// The new user joins the admin's tenant.
+#[instrument(skip(state, admin, new_user), fields(email = %new_user.email))]
+pub async fn create_user(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    JsonBody(new_user): JsonBody<NewUser>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let password_hash = hash_password(new_user.password).await?;
+    let users = Users { pool: &state.pool };
+    let user = users
+        .insert(
+            admin.user.tenant_id,
+            &new_user.email,
+            &password_hash,
+            new_user.role,
+        )
+        .await?;
+
+    tracing::info!("<-- 201");
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[derive(serde::Deserialize)]
+pub struct NewApiKey {
+    name: String,
+}
+
+#[derive(serde::Serialize)]
+struct CreatedApiKey {
+    name: String,
+    /// Only returned once; delivr keeps nothing but its digest.
+    key: String,
+}
+
+#[instrument(skip(state, admin, new_key))]
+pub async fn create_api_key(
+    admin: Require<roles::Admin>,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    JsonBody(new_key): JsonBody<NewApiKey>,
+) -> Result<impl IntoResponse> {
+    tracing::info!("-->");
+
+    let users = Users { pool: &state.pool };
+    let user = users.get_by_id(user_id).await?;
+    // users of other tenants are as good as nonexistent
+    if user.is_none_or(|user| user.tenant_id != admin.user.tenant_id) {
+        return Err(Error::NotFound(format!("User {user_id} does not exist")));
+    }
+
+    let key = format!("{API_KEY_PREFIX}{}", generate_secret());
+    let api_keys = ApiKeys { pool: &state.pool };
+    api_keys
+        .insert(&hash_secret(&key), user_id, &new_key.name)
+        .await?;
+
+    tracing::info!("<-- 201");
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKey {
+            name: new_key.name,
+            key,
+        }),
+    ))
+}
@@ -1,14 +1,29 @@
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use secrecy::{ExposeSecret, Secret};
 use tracing::instrument;
 
 use crate::config::Config;
-use crate::zoho::{Error, Query, Result, Token};
+use crate::zoho::{
+    Envelope, Error, Invoice, InvoiceDetail, InvoiceId, InvoiceList, InvoiceSummary, Organization,
+    OrganizationList, Query, RateLimiter, Region, Result, RetryPolicy, Token,
+};
+
+/// The largest page size Zoho Books accepts for list endpoints.
+const MAX_PER_PAGE: u32 = 200;
 
 #[derive(Debug, Clone)]
 pub struct Client {
     pub client_id: String,
     pub client_secret: Secret<String>,
     pub client: reqwest::Client,
+    pub region: Region,
+    pub accounts_url: String,
+    pub api_url: Option<String>,
+    pub scope: String,
+    pub redirect_uri: String,
+    pub concurrency: usize,
+    pub retry: RetryPolicy,
+    pub limiter: RateLimiter,
 }
 
 impl Client {
@@ -20,19 +35,68 @@ impl Client {
             client_id,
             client_secret,
             client: reqwest::Client::new(),
+            region: config.zoho.region,
+            accounts_url: config
+                .zoho
+                .accounts_url
+                .clone()
+                .unwrap_or_else(|| config.zoho.region.accounts_url().to_string()),
+            api_url: config.zoho.api_url.clone(),
+            scope: config.zoho.scope.clone(),
+            redirect_uri: config
+                .zoho
+                .redirect_uri
+                .clone()
+                .unwrap_or_else(|| format!("{}/oauth/callback", config.application.base_url)),
+            concurrency: config.zoho.concurrency.max(1),
+            retry: RetryPolicy::from(&config.zoho.retry),
+            limiter: RateLimiter::per_minute(config.zoho.requests_per_minute),
         }
     }
 
+    /// Books API endpoint on the data centre the token was issued for.
+    fn books_url(&self, token: &Token, path: &str) -> String {
+        let domain = if let Some(api_url) = &self.api_url {
+            api_url.trim_end_matches('/')
+        } else if token.api_domain.is_empty() {
+            self.region.api_domain()
+        } else {
+            token.api_domain.trim_end_matches('/')
+        };
+
+        format!("{domain}/books/v3/{path}")
+    }
+
+    /// Zoho's consent screen, which redirects back to `redirect_uri` with a grant code.
+    pub fn authorize_url(&self, state: &str) -> Result<String> {
+        let mut url = reqwest::Url::parse(&format!("{}/oauth/v2/auth", self.accounts_url))
+            .map_err(Error::custom)?;
+
+        url.query_pairs_mut()
+            .append_pair("scope", &self.scope)
+            .append_pair("client_id", &self.client_id)
+            .append_pair("response_type", "code")
+            .append_pair("access_type", "offline")
+            .append_pair("prompt", "consent")
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("state", state);
+
+        Ok(url.into())
+    }
+
     pub async fn request_token(&self, code: &str) -> Result<Token> {
         tracing::info!("--> Zoho");
+        // grant codes are single use, so this exchange is never retried
+        self.limiter.acquire().await;
         let response = self
             .client
-            .post("https://accounts.zoho.com/oauth/v2/token")
+            .post(format!("{}/oauth/v2/token", self.accounts_url))
             .form(&[
                 ("grant_type", "authorization_code"),
                 ("code", code),
                 ("client_id", &self.client_id),
                 ("client_secret", self.client_secret.expose_secret()),
+                ("redirect_uri", &self.redirect_uri),
             ])
             .send()
             .await?
@@ -40,139 +104,226 @@ impl Client {
             .await?;
 
         if let Some(error) = response.get("error") {
-            return Err(Error::response(format!("{error}")));
+            return Err(Error::oauth(error));
         }
 
         tracing::info!("<-- Zoho 200");
-        Ok(response.into())
+        Token::try_from(response)
     }
 
     pub async fn refresh_token(&self, token: &Token) -> Result<Token> {
         tracing::info!("--> Zoho");
-        let refresh_token = token.refresh_token.as_ref().unwrap();
-        let response = self
+        let refresh_token = token
+            .refresh_token
+            .as_ref()
+            .ok_or(Error::MissingRefreshToken)?;
+        let request = self
             .client
-            .post("https://accounts.zoho.com/oauth/v2/token")
+            .post(format!("{}/oauth/v2/token", self.accounts_url))
             .form(&[
                 ("grant_type", "refresh_token"),
                 ("refresh_token", refresh_token.expose_secret()),
                 ("client_id", &self.client_id),
                 ("client_secret", self.client_secret.expose_secret()),
-            ])
-            .send()
+            ]);
+        let response = self
+            .retry
+            .send(&self.limiter, request)
             .await?
             .json::<serde_json::Value>()
             .await?;
 
         if let Some(error) = response.get("error") {
-            return Err(Error::response(format!("{error}")));
+            return Err(Error::oauth(error));
         }
 
-        let mut token = Token::from(response);
+        let mut token = Token::try_from(response)?;
         token.refresh_token = Some(refresh_token.clone());
 
         tracing::info!("<-- Zoho 200");
         Ok(token)
     }
 
-    #[instrument(skip(self, token, query))]
+    /// The organizations the token's user can access; the listing is not paged.
+    #[instrument(skip(self, token))]
+    pub async fn get_organizations(&self, token: &Token) -> Result<Vec<Organization>> {
+        tracing::info!("--> Zoho");
+
+        let request = self
+            .client
+            .get(self.books_url(token, "organizations"))
+            .header(
+                "Authorization",
+                format!("Zoho-oauthtoken {}", token.access_token.expose_secret()),
+            );
+        let list = Envelope::<OrganizationList>::try_from(self.send(request).await?)?;
+
+        tracing::info!("<-- Zoho 200");
+        Ok(list.data.organizations)
+    }
+
+    /// Walks every page of the invoice listing and collects the entries.
     pub async fn get_invoices_with_query<'a>(
         &self,
         token: &Token,
         query: &'a Query<'a>,
-    ) -> Result<serde_json::Value> {
+    ) -> Result<Vec<InvoiceSummary>> {
+        self.invoice_summaries(token, query).try_collect().await
+    }
+
+    /// Fetches the full details of every invoice matching the query, with at
+    /// most `concurrency` detail requests in flight at once.
+    ///
+    /// Fails fast: the first detail request that errors aborts the remaining
+    /// ones and its error is returned. The result keeps the listing order when
+    /// the query sorts, and is ordered by `created_time` otherwise.
+    #[instrument(skip(self, token, query), fields(concurrency = self.concurrency))]
+    pub async fn get_invoices<'a>(
+        &self,
+        token: &Token,
+        query: &'a Query<'a>,
+    ) -> Result<Vec<Invoice>> {
+        let detail_query = query.organization();
+        let detail_query = &detail_query;
+
+        let mut invoices: Vec<(usize, Invoice)> = self
+            .invoice_summaries(token, query)
+            .enumerate()
+            .map(|(position, summary)| summary.map(|summary| (position, summary)))
+            .map_ok(|(position, summary)| async move {
+                let invoice = self
+                    .get_invoice(token, &summary.invoice_id, detail_query)
+                    .await?;
+                Ok((position, invoice))
+            })
+            .try_buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        if query.sort_column.is_some() {
+            invoices.sort_by_key(|(position, _)| *position);
+        } else {
+            invoices.sort_by_key(|(_, invoice)| invoice.created_time);
+        }
+
+        tracing::info!("<-- {} invoices", invoices.len());
+        Ok(invoices.into_iter().map(|(_, invoice)| invoice).collect())
+    }
+
+    /// Streams the invoice listing page by page, so callers can start
+    /// processing before the last page has been fetched.
+    pub fn invoice_summaries<'a>(
+        &'a self,
+        token: &'a Token,
+        query: &'a Query<'a>,
+    ) -> impl Stream<Item = Result<InvoiceSummary>> + 'a {
+        let first = query.page.unwrap_or(1);
+
+        stream::try_unfold(Some(first), move |page| async move {
+            let Some(page) = page else {
+                return Ok::<_, Error>(None);
+            };
+
+            let query = Query {
+                per_page: query.per_page.or(Some(MAX_PER_PAGE)),
+                ..query.with_page(page)
+            };
+            let list = self.get_invoices_page(token, &query).await?;
+
+            let next = list.has_more_page().then_some(page + 1);
+            Ok(Some((list.data.invoices, next)))
+        })
+        .map_ok(|ids| stream::iter(ids.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    #[instrument(skip(self, token, query), fields(page = query.page))]
+    pub async fn get_invoices_page<'a>(
+        &self,
+        token: &Token,
+        query: &'a Query<'a>,
+    ) -> Result<Envelope<InvoiceList>> {
         tracing::info!("--> Zoho");
 
-        let res = self
+        let request = self
             .client
-            .get("https://www.zohoapis.com/books/v3/invoices")
+            .get(self.books_url(token, "invoices"))
             .header(
                 "Authorization",
                 format!("Zoho-oauthtoken {}", token.access_token.expose_secret()),
             )
-            .query(&query)
-            .send()
-            .await;
-
-        let res = match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    tracing::info!("<-- Zoho: {}", res.status());
-                    res.json::<serde_json::Value>().await
-                } else {
-                    let res = res.json::<serde_json::Value>().await;
-                    let msg = res.unwrap()["message"].as_str().unwrap().to_string();
-                    return Err(Error::response(msg));
-                }
-            }
-            Err(err) => {
-                tracing::error!("{err:#?}");
-                return Err(Error::from(err));
-            }
-        };
-
-        let value = match res {
-            Ok(res) => res,
-            Err(err) => {
-                tracing::error!("{err:#?}");
-                return Err(Error::from(err));
-            }
-        };
+            .query(&query);
+        let list = Envelope::<InvoiceList>::try_from(self.send(request).await?)?;
 
-        Ok(value)
+        tracing::info!("<-- Zoho 200");
+        Ok(list)
     }
 
     #[instrument(skip(self, token, id, query))]
     pub async fn get_invoice<'a>(
         &self,
         token: &Token,
-        id: &'a str,
+        id: &'a InvoiceId,
+        query: &'a Query<'a>,
+    ) -> Result<Invoice> {
+        let (invoice, _) = self.get_invoice_with_payload(token, id, query).await?;
+        Ok(invoice)
+    }
+
+    /// The invoice along with the invoice object exactly as Zoho sent it.
+    pub async fn get_invoice_with_payload<'a>(
+        &self,
+        token: &Token,
+        id: &'a InvoiceId,
         query: &'a Query<'a>,
-    ) -> Result<serde_json::Value> {
+    ) -> Result<(Invoice, serde_json::Value)> {
         tracing::info!("--> Zoho");
 
-        let res = self
+        let request = self
             .client
-            .get(format!("https://www.zohoapis.com/books/v3/invoices/{id}"))
+            .get(self.books_url(token, &format!("invoices/{id}")))
             .header(
                 "Authorization",
                 format!("Zoho-oauthtoken {}", token.access_token.expose_secret()),
             )
-            .query(&query)
-            .send()
-            .await;
-
-        let res = match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    res.json::<serde_json::Value>().await
-                } else {
-                    let res = res.json::<serde_json::Value>().await;
-                    let msg = res.unwrap()["message"].as_str().unwrap().to_string();
-                    return Err(Error::response(msg));
-                }
-            }
-            Err(err) => {
-                tracing::error!("{err:#?}");
-                return Err(Error::from(err));
-            }
-        };
-
-        let value = match res {
-            Ok(res) => {
-                if res.get("invoice").is_some() {
-                    res.get("invoice").unwrap().clone()
-                } else {
-                    return Err(Error::custom("Invoice not found in the response"));
-                }
-            }
-            Err(err) => {
-                tracing::error!("{err:#?}");
-                return Err(Error::from(err));
-            }
-        };
+            .query(&query);
+        let mut value = self.send(request).await?;
+        let detail = Envelope::<InvoiceDetail>::try_from(value.clone())?;
 
         tracing::info!("<-- Zoho 200");
-        Ok(value)
+        Ok((detail.data.invoice, value["invoice"].take()))
+    }
+
+    /// Fetches the given invoices with their payloads, with at most
+    /// `concurrency` requests in flight at once. Fails fast like `get_invoices`.
+    pub async fn get_invoices_with_payloads<'a>(
+        &self,
+        token: &Token,
+        ids: &'a [InvoiceId],
+        query: &'a Query<'a>,
+    ) -> Result<Vec<(Invoice, serde_json::Value)>> {
+        let requests: Vec<_> = ids
+            .iter()
+            .map(|id| self.get_invoice_with_payload(token, id, query))
+            .collect();
+
+        stream::iter(requests)
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Sends a Books API request, turning error statuses into `Error::Response`.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+        let res = self.retry.send(&self.limiter, request).await?;
+        let status = res.status();
+
+        if status.is_success() {
+            return Ok(res.json::<serde_json::Value>().await?);
+        }
+
+        // error bodies are JSON, except when something in front of Zoho answered
+        let body = res.json::<serde_json::Value>().await.unwrap_or_default();
+        Err(Error::response(status.as_u16(), &body))
     }
 }
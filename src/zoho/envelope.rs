@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+/// Every Books API response: a status `code` and `message` next to the
+/// payload, plus `page_context` on list endpoints.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Envelope<T> {
+    pub code: i64,
+    pub message: String,
+    #[serde(default)]
+    pub page_context: Option<PageContext>,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, serde::Serialize)]
+pub struct PageContext {
+    pub page: u32,
+    pub per_page: u32,
+    pub has_more_page: bool,
+}
+
+impl<T> Envelope<T> {
+    pub fn has_more_page(&self) -> bool {
+        self.page_context
+            .as_ref()
+            .is_some_and(|page_context| page_context.has_more_page)
+    }
+}
@@ -4,7 +4,22 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug, From)]
 pub enum Error {
-    Response(String),
+    /// Zoho answered with an error. `code` is Zoho's own error code, OAuth
+    /// errors only carry a message such as `invalid_code`.
+    Response {
+        status: u16,
+        code: Option<i64>,
+        message: String,
+    },
+
+    MissingRefreshToken,
+
+    /// A payload from Zoho did not have the expected shape. `path` points at
+    /// the offending field, such as `line_items[2].rate`.
+    Parse {
+        path: String,
+        message: String,
+    },
 
     #[from]
     Custom(String),
@@ -20,12 +35,97 @@ impl Error {
         Self::Custom(val.to_string())
     }
 
-    pub fn response(val: impl std::fmt::Display) -> Self {
-        tracing::error!("<-- Zoho: {val}");
-        Self::Response(val.to_string())
+    /// An error from the Books API, whose body looks like `{"code": 1002, "message": "..."}`.
+    pub fn response(status: u16, body: &serde_json::Value) -> Self {
+        let code = body.get("code").and_then(|code| code.as_i64());
+        let message = body
+            .get("message")
+            .and_then(|message| message.as_str())
+            .unwrap_or("Zoho returned an error")
+            .to_string();
+
+        tracing::error!(status, ?code, "<-- Zoho: {message}");
+        Self::Response {
+            status,
+            code,
+            message,
+        }
+    }
+
+    /// An error from the accounts server, whose body looks like `{"error": "invalid_code"}`.
+    pub fn oauth(error: &serde_json::Value) -> Self {
+        let message = error.as_str().map_or(error.to_string(), str::to_string);
+
+        tracing::error!("<-- Zoho: {message}");
+        Self::Response {
+            status: 200,
+            code: None,
+            message,
+        }
+    }
+
+    pub fn parse(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().to_string();
+        let message = err.into_inner().to_string();
+
+        tracing::error!(%path, "<-- Zoho: unexpected payload: {message}");
+        Self::Parse { path, message }
+    }
+
+    /// Whether the grant behind the token is gone and Zoho must be connected again.
+    pub fn requires_reauthorization(&self) -> bool {
+        matches!(self.kind(), ErrorKind::ReauthorizationRequired)
+    }
+
+    /// What the error means for delivr, judged from Zoho's error codes.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::MissingRefreshToken => ErrorKind::ReauthorizationRequired,
+            Self::Response {
+                message,
+                code: None,
+                ..
+            } if message == "invalid_code" => ErrorKind::ReauthorizationRequired,
+            Self::Response {
+                status,
+                code: Some(code),
+                ..
+            } => match code {
+                // the requested record or URL does not exist
+                5 | 1002 => ErrorKind::NotFound,
+                // invalid parameter values
+                1 | 2 | 4 => ErrorKind::InvalidRequest,
+                // the organization is unknown or not accessible with our token
+                6041 => ErrorKind::InvalidOrganization,
+                // the access token was rejected
+                14 | 57 => ErrorKind::Unauthorized,
+                // per-minute and per-day API limits
+                44 | 45 => ErrorKind::RateLimited,
+                _ if *status == 404 => ErrorKind::NotFound,
+                _ if *status == 429 => ErrorKind::RateLimited,
+                _ => ErrorKind::Upstream,
+            },
+            Self::Response { status: 429, .. } => ErrorKind::RateLimited,
+            Self::Reqwest(err) if err.is_timeout() || err.is_connect() => ErrorKind::Unavailable,
+            _ => ErrorKind::Upstream,
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    InvalidRequest,
+    InvalidOrganization,
+    Unauthorized,
+    ReauthorizationRequired,
+    RateLimited,
+    /// Zoho could not be reached.
+    Unavailable,
+    /// Zoho failed or answered with something unexpected.
+    Upstream,
+}
+
 impl From<&str> for Error {
     fn from(val: &str) -> Self {
         Self::Custom(val.to_string())
@@ -34,6 +134,11 @@ impl From<&str> for Error {
 
 // endregion: --- Custom
 
+/// Deserializes a Zoho payload, reporting where it went wrong instead of panicking.
+pub fn from_value<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T> {
+    serde_path_to_error::deserialize(value).map_err(Error::parse)
+}
+
 // region:    --- Error Boilerplate
 
 impl core::fmt::Display for Error {
@@ -45,3 +150,27 @@ impl core::fmt::Display for Error {
 impl std::error::Error for Error {}
 
 // endregion: --- Error Boilerplate
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn kind_from_zoho_codes() {
+        let not_found = Error::response(
+            404,
+            &json!({ "code": 1002, "message": "Invoice does not exist." }),
+        );
+        assert_eq!(not_found.kind(), ErrorKind::NotFound);
+
+        let organization = Error::response(400, &json!({ "code": 6041, "message": "..." }));
+        assert_eq!(organization.kind(), ErrorKind::InvalidOrganization);
+
+        let unknown = Error::response(500, &json!({ "message": "oops" }));
+        assert_eq!(unknown.kind(), ErrorKind::Upstream);
+
+        assert!(Error::oauth(&json!("invalid_code")).requires_reauthorization());
+        assert!(!not_found.requires_reauthorization());
+    }
+}
@@ -0,0 +1,44 @@
+/// Declares a newtype around one of Zoho's string IDs, so that an invoice ID
+/// cannot be passed where a customer ID is expected.
+macro_rules! zoho_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+    };
+}
+
+zoho_id!(InvoiceId);
+zoho_id!(CustomerId);
+zoho_id!(SalespersonId);
+zoho_id!(LineItemId);
+zoho_id!(ItemId);
+zoho_id!(TaxId);
+zoho_id!(PaymentId);
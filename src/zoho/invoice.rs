@@ -1,52 +1,172 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{
     ser::{SerializeStruct, Serializer},
     Deserialize,
 };
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
-pub struct InvoiceIDs {
-    #[serde(rename = "invoices")]
-    pub inner: Vec<InvoiceID>,
-}
+use super::error::{from_value, Error};
+use super::ids::{CustomerId, InvoiceId, ItemId, LineItemId, PaymentId, SalespersonId, TaxId};
+use super::money::{decimal, Money};
+use super::profit::Breakdown;
+use super::Envelope;
 
+/// The payload of `GET /invoices`.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
-pub struct InvoiceID {
-    #[serde(rename = "invoice_id")]
-    pub id: String,
+pub struct InvoiceList {
+    pub invoices: Vec<InvoiceSummary>,
 }
 
-impl From<serde_json::Value> for InvoiceIDs {
-    fn from(value: serde_json::Value) -> Self {
-        serde_json::from_value(value).unwrap()
+impl TryFrom<serde_json::Value> for Envelope<InvoiceList> {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        from_value(value)
     }
 }
 
+/// An entry of the invoice listing, which carries far less than the full invoice.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
-pub struct Invoices {
-    pub invoices: Vec<Invoice>,
+pub struct InvoiceSummary {
+    pub invoice_id: InvoiceId,
+    pub invoice_number: String,
+    pub status: InvoiceStatus,
+    pub customer_id: CustomerId,
+    pub customer_name: String,
+    pub date: NaiveDate,
+    pub total: Money,
+    #[serde(default)]
+    pub balance: Money,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub salesperson_name: Option<String>,
+    #[serde(deserialize_with = "zoho_datetime")]
+    pub created_time: DateTime<Utc>,
+    #[serde(default, deserialize_with = "zoho_datetime_option")]
+    pub last_modified_time: Option<DateTime<Utc>>,
 }
 
-impl From<serde_json::Value> for Invoices {
-    fn from(value: serde_json::Value) -> Self {
-        serde_json::from_value(value).unwrap()
+/// The payload of `GET /invoices/:id`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct InvoiceDetail {
+    pub invoice: Invoice,
+}
+
+impl TryFrom<serde_json::Value> for Envelope<InvoiceDetail> {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        from_value(value)
     }
 }
 
+/// Whether discounts are given per line item or on the invoice as a whole.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountType {
+    #[default]
+    ItemLevel,
+    EntityLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Draft,
+    Sent,
+    Viewed,
+    Overdue,
+    Unpaid,
+    PartiallyPaid,
+    Paid,
+    Void,
+    PendingApproval,
+    Approved,
+    /// A status this version of delivr does not know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A full invoice as returned by `GET /invoices/:id`.
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Invoice {
-    #[serde(deserialize_with = "de_deserialize")]
-    pub created_time: chrono::NaiveDateTime,
+    pub invoice_id: InvoiceId,
+    pub invoice_number: String,
+    pub status: InvoiceStatus,
+    #[serde(deserialize_with = "zoho_datetime")]
+    pub created_time: DateTime<Utc>,
+    #[serde(default, deserialize_with = "zoho_datetime_option")]
+    pub last_modified_time: Option<DateTime<Utc>>,
+    /// The business date of the invoice, as chosen in Zoho.
+    pub date: NaiveDate,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub due_date: Option<String>,
+    pub customer_id: CustomerId,
     pub customer_name: String,
-    pub date: String,
-    pub invoice_id: String,
+    /// Zoho leaves these out, or empty, when no salesperson is assigned.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub salesperson_id: Option<SalespersonId>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub salesperson_name: Option<String>,
+    #[serde(default)]
+    pub currency_code: String,
     pub line_items: Vec<LineItem>,
-    pub salesperson_name: String,
-    pub total: f64,
+    #[serde(default)]
+    pub discount_type: DiscountType,
+    /// Whether an invoice-level discount is taken off before tax is added.
+    #[serde(default = "default_true")]
+    pub is_discount_before_tax: bool,
+    /// Whether rates and line totals already include tax.
+    #[serde(default)]
+    pub is_inclusive_tax: bool,
+    #[serde(default)]
+    pub sub_total: Money,
+    #[serde(default)]
+    pub discount_total: Money,
+    #[serde(default)]
+    pub tax_total: Money,
+    #[serde(default)]
+    pub shipping_charge: Money,
+    #[serde(default)]
+    pub adjustment: Money,
+    pub total: Money,
+    #[serde(default)]
+    pub balance: Money,
+    #[serde(default)]
+    pub taxes: Vec<Tax>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    #[serde(default)]
+    pub payments: Vec<Payment>,
+    #[serde(default)]
+    pub billing_address: Option<Address>,
+    #[serde(default)]
+    pub shipping_address: Option<Address>,
 }
 
 impl Invoice {
-    pub fn profit(&self) -> f64 {
-        self.line_items.iter().map(|li| li.profit()).sum::<f64>()
+    /// Net profit after discounts, see [`Breakdown`].
+    /// `None` when the purchase rate of any line item is unknown.
+    pub fn profit(&self) -> Option<Money> {
+        self.breakdown().profit
+    }
+
+    /// Whether the invoice counts towards sales: it has been issued and not
+    /// voided since. Drafts, and invoices still waiting for approval, may yet
+    /// change or never be sent.
+    pub fn is_sale(&self) -> bool {
+        !matches!(
+            self.status,
+            InvoiceStatus::Draft
+                | InvoiceStatus::PendingApproval
+                | InvoiceStatus::Approved
+                | InvoiceStatus::Void
+        )
+    }
+
+    pub fn custom_field(&self, label: &str) -> Option<&CustomField> {
+        self.custom_fields
+            .iter()
+            .find(|field| field.label == label || field.api_name.as_deref() == Some(label))
     }
 }
 
@@ -55,68 +175,282 @@ impl serde::Serialize for Invoice {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Invoice", 8)?;
+        let mut state = serializer.serialize_struct("Invoice", 30)?;
+        state.serialize_field("invoice_id", &self.invoice_id)?;
+        state.serialize_field("invoice_number", &self.invoice_number)?;
+        state.serialize_field("status", &self.status)?;
         state.serialize_field("created_time", &self.created_time)?;
-        state.serialize_field("customer_name", &self.customer_name)?;
+        state.serialize_field("last_modified_time", &self.last_modified_time)?;
         state.serialize_field("date", &self.date)?;
-        state.serialize_field("invoice_id", &self.invoice_id)?;
-        state.serialize_field("line_items", &self.line_items)?;
+        state.serialize_field("due_date", &self.due_date)?;
+        state.serialize_field("customer_id", &self.customer_id)?;
+        state.serialize_field("customer_name", &self.customer_name)?;
+        state.serialize_field("salesperson_id", &self.salesperson_id)?;
         state.serialize_field("salesperson_name", &self.salesperson_name)?;
+        state.serialize_field("currency_code", &self.currency_code)?;
+        state.serialize_field("discount_type", &self.discount_type)?;
+        state.serialize_field("is_discount_before_tax", &self.is_discount_before_tax)?;
+        state.serialize_field("is_inclusive_tax", &self.is_inclusive_tax)?;
+        let line_items: Vec<_> = self
+            .line_items
+            .iter()
+            .zip(self.line_breakdowns())
+            .map(|(line, breakdown)| PricedLineItem::new(line, breakdown))
+            .collect();
+        state.serialize_field("line_items", &line_items)?;
+        state.serialize_field("sub_total", &self.sub_total)?;
+        state.serialize_field("discount_total", &self.discount_total)?;
+        state.serialize_field("tax_total", &self.tax_total)?;
+        state.serialize_field("shipping_charge", &self.shipping_charge)?;
+        state.serialize_field("adjustment", &self.adjustment)?;
         state.serialize_field("total", &self.total)?;
-        state.serialize_field("profit", &self.profit())?;
+        state.serialize_field("balance", &self.balance)?;
+        state.serialize_field("taxes", &self.taxes)?;
+        state.serialize_field("custom_fields", &self.custom_fields)?;
+        state.serialize_field("payments", &self.payments)?;
+        state.serialize_field("billing_address", &self.billing_address)?;
+        state.serialize_field("shipping_address", &self.shipping_address)?;
+        let breakdown = self.breakdown();
+        state.serialize_field("profit", &breakdown.profit)?;
+        state.serialize_field("breakdown", &breakdown)?;
         state.end()
     }
 }
 
-// 2024-05-27T19:26:32+0800
-fn de_deserialize<'de, D>(deserializer: D) -> Result<chrono::NaiveDateTime, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    let datetime = chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%z")
-        .map_err(serde::de::Error::custom)?;
-    Ok(datetime)
-}
+impl TryFrom<serde_json::Value> for Invoice {
+    type Error = Error;
 
-impl From<serde_json::Value> for Invoice {
-    fn from(value: serde_json::Value) -> Self {
-        serde_json::from_value(value).unwrap()
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        from_value(value)
     }
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct LineItem {
+    pub line_item_id: LineItemId,
+    /// Empty for lines typed in directly instead of picked from the item list.
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub item_id: Option<ItemId>,
     pub name: String,
-    pub rate: f64,
-    pub quantity: f64,
-    pub purchase_rate: f64,
-    pub item_total: f64,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub unit: Option<String>,
+    pub rate: Money,
+    #[serde(deserialize_with = "decimal")]
+    pub quantity: BigDecimal,
+    #[serde(default)]
+    pub discount_amount: Money,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub tax_id: Option<TaxId>,
+    #[serde(default, deserialize_with = "decimal")]
+    pub tax_percentage: BigDecimal,
+    /// Zoho leaves this out for items without a purchase price, such as services.
+    #[serde(default)]
+    pub purchase_rate: Option<Money>,
+    pub item_total: Money,
 }
 
-impl LineItem {
-    pub fn profit(&self) -> f64 {
-        (self.rate - self.purchase_rate) * self.quantity
-    }
-}
-
-// Implement custom serialization to include `profit`
 impl serde::Serialize for LineItem {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("LineItem", 6)?;
-        state.serialize_field("item_profit", &self.profit())?;
+        let mut state = serializer.serialize_struct("LineItem", 12)?;
         state.serialize_field("item_total", &self.item_total)?;
         state.serialize_field("name", &self.name)?;
         state.serialize_field("purchase_rate", &self.purchase_rate)?;
         state.serialize_field("quantity", &self.quantity)?;
         state.serialize_field("rate", &self.rate)?;
+        state.serialize_field("line_item_id", &self.line_item_id)?;
+        state.serialize_field("item_id", &self.item_id)?;
+        state.serialize_field("description", &self.description)?;
+        state.serialize_field("unit", &self.unit)?;
+        state.serialize_field("discount_amount", &self.discount_amount)?;
+        state.serialize_field("tax_id", &self.tax_id)?;
+        state.serialize_field("tax_percentage", &self.tax_percentage)?;
         state.end()
     }
 }
 
+/// A line item as it leaves delivr, with the profit it makes within its invoice.
+#[derive(serde::Serialize)]
+struct PricedLineItem<'a> {
+    #[serde(flatten)]
+    line: &'a LineItem,
+    item_profit: Option<Money>,
+    breakdown: Breakdown,
+}
+
+impl<'a> PricedLineItem<'a> {
+    fn new(line: &'a LineItem, breakdown: Breakdown) -> Self {
+        Self {
+            line,
+            item_profit: breakdown.profit.clone(),
+            breakdown,
+        }
+    }
+}
+
+/// A tax applied to the invoice, summed over its line items.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Tax {
+    pub tax_name: String,
+    pub tax_amount: Money,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct CustomField {
+    #[serde(default)]
+    pub customfield_id: Option<String>,
+    pub label: String,
+    #[serde(default)]
+    pub api_name: Option<String>,
+    #[serde(default)]
+    pub data_type: Option<String>,
+    /// Its type depends on `data_type`, so it is kept as Zoho sent it.
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Payment {
+    pub payment_id: PaymentId,
+    #[serde(default)]
+    pub payment_number: Option<String>,
+    pub date: String,
+    pub amount: Money,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub payment_mode: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub reference_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Address {
+    pub attention: String,
+    pub address: String,
+    pub street2: String,
+    pub city: String,
+    pub state: String,
+    pub zip: String,
+    pub country: String,
+    pub phone: String,
+}
+
+/// Zoho timestamps carry the organization's offset, e.g. `2024-05-27T19:26:32+0800`;
+/// they are kept as UTC instants.
+fn zoho_datetime<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_zoho_datetime(&s).map_err(serde::de::Error::custom)
+}
+
+fn zoho_datetime_option<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(s) => parse_zoho_datetime(s)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+fn parse_zoho_datetime(s: &str) -> chrono::ParseResult<DateTime<Utc>> {
+    DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%z").map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Zoho sends `""` rather than leaving out fields that have no value.
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: From<String>,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+    Ok(s.filter(|s| !s.is_empty()).map(T::from))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Builds invoices for unit tests. Unless told otherwise an invoice is sent on
+/// 2024-05-27 to customer 10, without a salesperson, and has a single line
+/// item of ten units at 10.00 with an unknown cost.
+#[cfg(test)]
+pub(crate) struct InvoiceBuilder {
+    value: serde_json::Value,
+}
+
+#[cfg(test)]
+impl InvoiceBuilder {
+    pub(crate) fn new(id: &str) -> Self {
+        let value = serde_json::json!({
+            "invoice_id": id,
+            "invoice_number": format!("INV-{id:0>6}"),
+            "status": "sent",
+            "created_time": "2024-05-27T19:26:32+0800",
+            "customer_id": "10",
+            "customer_name": "customer 10",
+            "date": "2024-05-27",
+            "total": 100.0,
+            "line_items": [
+                { "line_item_id": id, "name": "item", "rate": 10.0, "quantity": 10.0,
+                  "item_total": 100.0 }
+            ]
+        });
+        Self { value }
+    }
+
+    pub(crate) fn status(mut self, status: &str) -> Self {
+        self.value["status"] = status.into();
+        self
+    }
+
+    pub(crate) fn date(mut self, date: &str) -> Self {
+        self.value["date"] = date.into();
+        self
+    }
+
+    pub(crate) fn customer(mut self, customer_id: &str) -> Self {
+        self.value["customer_id"] = customer_id.into();
+        self.value["customer_name"] = format!("customer {customer_id}").into();
+        self
+    }
+
+    pub(crate) fn salesperson(mut self, salesperson_id: &str) -> Self {
+        self.value["salesperson_id"] = salesperson_id.into();
+        self.value["salesperson_name"] = format!("salesperson {salesperson_id}").into();
+        self
+    }
+
+    /// What the line item, and so the invoice, comes to after its discount.
+    pub(crate) fn item_total(mut self, item_total: f64) -> Self {
+        self.value["total"] = item_total.into();
+        self.value["line_items"][0]["item_total"] = item_total.into();
+        self
+    }
+
+    pub(crate) fn purchase_rate(mut self, purchase_rate: f64) -> Self {
+        self.value["line_items"][0]["purchase_rate"] = purchase_rate.into();
+        self
+    }
+
+    /// The payload as Zoho would send it, for tests that tamper with it.
+    pub(crate) fn json(self) -> serde_json::Value {
+        self.value
+    }
+
+    pub(crate) fn build(self) -> crate::error::Result<Invoice> {
+        Ok(Invoice::try_from(self.value)?)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -130,31 +464,128 @@ mod test {
         // Parse the string into a serde_json::Value
         let json_value: serde_json::Value = serde_json::from_str(&data)?;
 
-        let invoice = json_value
-            .get("invoice")
-            .ok_or("invoice not found")
-            .unwrap();
-        let _ = Invoice::from(invoice.clone());
+        let response = Envelope::<InvoiceDetail>::try_from(json_value)?;
+        let invoice = response.data.invoice;
+
+        assert_eq!(response.code, 0);
+        assert_eq!(invoice.invoice_id.as_str(), "4332607000000182746");
+        assert_eq!(invoice.status, InvoiceStatus::Draft);
+        assert_eq!(invoice.salesperson_name.as_deref(), Some("Yuki"));
+        assert_eq!(
+            invoice.line_items[0].item_id,
+            Some(ItemId::from("4332607000000182461"))
+        );
+        assert_eq!(invoice.line_items[0].description, None);
+        assert_eq!(
+            invoice.created_time,
+            "2024-05-27T11:26:32Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(invoice.date, NaiveDate::from_ymd_opt(2024, 5, 27).unwrap());
+        assert_eq!(invoice.profit(), Some(Money::from(20)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoice_list_page_context() -> Result<()> {
+        let value = serde_json::json!({
+            "code": 0,
+            "message": "success",
+            "invoices": [{
+                "invoice_id": "1",
+                "invoice_number": "INV-000001",
+                "status": "partially_paid",
+                "customer_id": "10",
+                "customer_name": "customer",
+                "date": "2024-05-27",
+                "total": 110.0,
+                "balance": 10.0,
+                "created_time": "2024-05-27T19:26:32+0800",
+                "last_modified_time": ""
+            }],
+            "page_context": { "page": 1, "per_page": 2, "has_more_page": true }
+        });
+
+        let list = Envelope::<InvoiceList>::try_from(value)?;
 
+        assert_eq!(list.data.invoices.len(), 1);
+        assert_eq!(list.data.invoices[0].status, InvoiceStatus::PartiallyPaid);
+        assert_eq!(list.data.invoices[0].last_modified_time, None);
+        assert!(list.has_more_page());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_status_is_tolerated() -> Result<()> {
+        let status: InvoiceStatus = serde_json::from_value(serde_json::json!("written_off"))?;
+
+        assert_eq!(status, InvoiceStatus::Unknown);
         Ok(())
     }
 
     #[test]
     fn line_item_serialize() -> Result<()> {
         let line_item = LineItem {
+            line_item_id: LineItemId::from("1"),
+            item_id: None,
             name: "name".to_string(),
-            rate: 11.0,
-            quantity: 10.0,
-            purchase_rate: 10.0,
-            item_total: 110.0,
+            description: None,
+            unit: None,
+            rate: Money::from(11),
+            quantity: BigDecimal::from(10),
+            discount_amount: Money::zero(),
+            tax_id: None,
+            tax_percentage: BigDecimal::from(0),
+            purchase_rate: Some(Money::from(10)),
+            item_total: Money::from(110),
         };
 
         let serialized = serde_json::to_string(&line_item)?;
 
         assert_eq!(
             serialized,
-            r#"{"item_profit":10.0,"item_total":110.0,"name":"name","purchase_rate":10.0,"quantity":10.0,"rate":11.0}"#
+            r#"{"item_total":"110.00","name":"name","purchase_rate":"10.00","quantity":"10","rate":"11.00","line_item_id":"1","item_id":null,"description":null,"unit":null,"discount_amount":"0.00","tax_id":null,"tax_percentage":"0"}"#
         );
         Ok(())
     }
+
+    #[test]
+    fn invoice_serialize_includes_the_breakdown() -> Result<()> {
+        let data = std::fs::read_to_string("tests/invoice_response.txt")?;
+        let response =
+            Envelope::<InvoiceDetail>::try_from(serde_json::from_str::<serde_json::Value>(&data)?)?;
+
+        let serialized = serde_json::to_value(&response.data.invoice)?;
+
+        assert_eq!(serialized["profit"], "20.00");
+        assert_eq!(serialized["breakdown"]["cost"], "100.00");
+        assert_eq!(serialized["line_items"][0]["item_profit"], "20.00");
+        assert_eq!(
+            serialized["line_items"][0]["breakdown"]["revenue"],
+            "120.00"
+        );
+        assert_eq!(serialized["line_items"][0]["name"], "Cosmetics RM5 Tracked");
+        Ok(())
+    }
+
+    #[test]
+    fn optional_fields_may_be_missing() -> Result<()> {
+        let invoice = InvoiceBuilder::new("1").build()?;
+
+        assert_eq!(invoice.salesperson_name, None);
+        assert_eq!(invoice.line_items[0].purchase_rate, None);
+        assert_eq!(invoice.profit(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_error_carries_the_path() {
+        let mut value = InvoiceBuilder::new("1").json();
+        value["line_items"][0]["rate"] = serde_json::json!("eleven");
+
+        match Invoice::try_from(value) {
+            Err(Error::Parse { path, .. }) => assert_eq!(path, "line_items[0].rate"),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
 }
@@ -4,11 +4,35 @@ pub use token::Token;
 mod client;
 pub use client::Client;
 
+mod region;
+pub use region::Region;
+
+mod retry;
+pub use retry::{RateLimiter, RetryPolicy};
+
 mod query;
-pub use query::{Query, QueryBuilder};
+pub use query::{OwnedQuery, Query, QueryBuilder, SortColumn, SortOrder, StatusFilter};
+
+mod ids;
+pub use ids::*;
+
+mod envelope;
+pub use envelope::{Envelope, PageContext};
+
+mod money;
+pub use money::Money;
 
 mod invoice;
 pub use invoice::*;
 
+mod organization;
+pub use organization::{Organization, OrganizationList};
+
+mod profit;
+pub use profit::Breakdown;
+
+mod summary;
+pub use summary::Summary;
+
 mod error;
-pub use error::{Error, Result};
+pub use error::{Error, ErrorKind, Result};
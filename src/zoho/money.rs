@@ -0,0 +1,209 @@
+use std::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Zero};
+use serde::Deserialize;
+
+/// Amounts are kept exact while adding up and only rounded when shown.
+pub const SCALE: i64 = 2;
+
+/// An exact amount of money in the organization's currency.
+///
+/// Arithmetic never rounds. Display and serialization round to [`SCALE`]
+/// decimal places, halves away from zero, and serialize as a string such as
+/// `"120.00"` so JavaScript clients do not turn it back into a float.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(BigDecimal);
+
+impl Money {
+    pub fn new(amount: BigDecimal) -> Self {
+        Self(amount)
+    }
+
+    pub fn zero() -> Self {
+        Self(BigDecimal::zero())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn amount(&self) -> &BigDecimal {
+        &self.0
+    }
+
+    /// The amount at [`SCALE`] decimal places, rounding halves away from zero.
+    pub fn rounded(&self) -> BigDecimal {
+        self.0.round(SCALE).with_scale(SCALE)
+    }
+}
+
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.rounded())
+    }
+}
+
+impl FromStr for Money {
+    type Err = bigdecimal::ParseBigDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BigDecimal::from_str(s).map(Self)
+    }
+}
+
+impl From<BigDecimal> for Money {
+    fn from(amount: BigDecimal) -> Self {
+        Self(amount)
+    }
+}
+
+impl From<i64> for Money {
+    fn from(amount: i64) -> Self {
+        Self(BigDecimal::from(amount))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl<'a> Add<&'a Money> for Money {
+    type Output = Money;
+
+    fn add(self, rhs: &'a Money) -> Money {
+        Money(self.0 + &rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl<'a> Sub<&'a Money> for &'a Money {
+    type Output = Money;
+
+    fn sub(self, rhs: &'a Money) -> Money {
+        Money(&self.0 - &rhs.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+/// Price times quantity.
+impl<'a> Mul<&'a BigDecimal> for &'a Money {
+    type Output = Money;
+
+    fn mul(self, rhs: &'a BigDecimal) -> Money {
+        Money(&self.0 * rhs)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.fold(Money::zero(), Add::add)
+    }
+}
+
+impl serde::Serialize for Money {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&self.rounded())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        decimal(deserializer).map(Money)
+    }
+}
+
+/// Reads a decimal that Zoho sends as a JSON number, a string, or `""` when unset.
+///
+/// JSON numbers arrive as `f64`; they are converted through their shortest
+/// round-tripping representation, so `6.1` becomes exactly `6.1`.
+pub fn decimal<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Lenient {
+        Number(f64),
+        Text(String),
+    }
+
+    match Lenient::deserialize(deserializer)? {
+        Lenient::Number(n) => {
+            BigDecimal::from_str(&n.to_string()).map_err(serde::de::Error::custom)
+        }
+        Lenient::Text(s) if s.is_empty() => Ok(BigDecimal::zero()),
+        Lenient::Text(s) => BigDecimal::from_str(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn sums_without_drift() {
+        let total: Money = std::iter::repeat_n(money("0.1"), 10).sum();
+
+        assert_eq!(total, money("1"));
+        assert_eq!(total.to_string(), "1.00");
+    }
+
+    #[test]
+    fn rounds_halves_away_from_zero() {
+        assert_eq!(money("2.345").to_string(), "2.35");
+        assert_eq!(money("-2.345").to_string(), "-2.35");
+        assert_eq!(money("2.344").to_string(), "2.34");
+        assert_eq!(money("7").to_string(), "7.00");
+    }
+
+    #[test]
+    fn serde_round_trip() -> Result<(), serde_json::Error> {
+        let from_number: Money = serde_json::from_value(serde_json::json!(6.1))?;
+        let from_string: Money = serde_json::from_value(serde_json::json!("6.10"))?;
+        let from_empty: Money = serde_json::from_value(serde_json::json!(""))?;
+
+        assert_eq!(from_number, from_string);
+        assert!(from_empty.is_zero());
+        assert_eq!(
+            serde_json::to_value(&from_number)?,
+            serde_json::json!("6.10")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use super::error::{from_value, Error};
+use super::Envelope;
+
+/// The payload of `GET /organizations`: every organization the token's user
+/// has access to.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct OrganizationList {
+    pub organizations: Vec<Organization>,
+}
+
+impl TryFrom<serde_json::Value> for Envelope<OrganizationList> {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        from_value(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Organization {
+    pub organization_id: String,
+    pub name: String,
+    /// The organization Zoho opens by default for the user.
+    #[serde(default)]
+    pub is_default_org: bool,
+    #[serde(default)]
+    pub currency_code: String,
+    /// An IANA name such as `Asia/Kuala_Lumpur`.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+}
+
+impl Organization {
+    /// The organization to use when none is asked for: the one Zoho opens by
+    /// default, or the only one there is.
+    pub fn default_of(organizations: &[Organization]) -> Option<&Organization> {
+        organizations
+            .iter()
+            .find(|organization| organization.is_default_org)
+            .or(match organizations {
+                [only] => Some(only),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zoho::Result;
+
+    #[test]
+    fn deserialize_organizations() -> Result<()> {
+        let value = serde_json::json!({
+            "code": 0,
+            "message": "success",
+            "organizations": [{
+                "organization_id": "820117212",
+                "name": "Delivr Sdn Bhd",
+                "contact_name": "Yuki",
+                "email": "yuki@example.com",
+                "is_default_org": true,
+                "plan_type": 12,
+                "currency_code": "MYR",
+                "time_zone": "Asia/Kuala_Lumpur",
+            }]
+        });
+
+        let list = Envelope::<OrganizationList>::try_from(value)?;
+        let organization = &list.data.organizations[0];
+
+        assert_eq!(organization.organization_id, "820117212");
+        assert!(organization.is_default_org);
+        assert_eq!(organization.time_zone.as_deref(), Some("Asia/Kuala_Lumpur"));
+        Ok(())
+    }
+
+    fn organization(organization_id: &str, is_default_org: bool) -> Organization {
+        Organization {
+            organization_id: organization_id.to_string(),
+            name: format!("Organization {organization_id}"),
+            is_default_org,
+            currency_code: "MYR".to_string(),
+            time_zone: None,
+        }
+    }
+
+    #[test]
+    fn default_organization_is_discovered() {
+        let flagged = [organization("1", false), organization("2", true)];
+        let only = [organization("1", false)];
+        let ambiguous = [organization("1", false), organization("2", false)];
+
+        assert_eq!(Organization::default_of(&flagged), Some(&flagged[1]));
+        assert_eq!(Organization::default_of(&only), Some(&only[0]));
+        assert_eq!(Organization::default_of(&ambiguous), None);
+    }
+}
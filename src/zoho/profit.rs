@@ -0,0 +1,309 @@
+use bigdecimal::BigDecimal;
+
+use super::invoice::{DiscountType, Invoice, LineItem};
+use super::money::Money;
+
+/// Where the money of a line item or an invoice goes, all amounts exclusive of tax.
+///
+/// `profit` is `revenue - discount - cost`. Tax is collected for the
+/// government and never counts towards profit. Shipping and adjustments are
+/// reported on their own but left out of profit too, as delivr does not know
+/// what the delivery cost. They only appear on the invoice, as Zoho does not
+/// split them over line items.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Breakdown {
+    /// Rate times quantity, before any discount.
+    pub revenue: Money,
+    /// Line discounts plus the line's share of an invoice-level discount.
+    pub discount: Money,
+    pub tax: Money,
+    pub shipping: Money,
+    pub adjustment: Money,
+    /// Purchase rate times quantity, `None` when a purchase rate is unknown.
+    pub cost: Option<Money>,
+    pub profit: Option<Money>,
+}
+
+impl Invoice {
+    /// One breakdown per line item, in the order of `line_items`.
+    ///
+    /// An invoice-level discount is split over the lines in proportion to
+    /// their totals. With tax-inclusive rates the tax is backed out of every
+    /// amount using the line's tax percentage. Split amounts are rounded to
+    /// cents with the remainder on the last line, so the lines add up to the
+    /// invoice.
+    pub fn line_breakdowns(&self) -> Vec<Breakdown> {
+        let lines_total: Money = self.line_items.iter().map(|li| &li.item_total).sum();
+        let mut shares = vec![Money::zero(); self.line_items.len()];
+        if self.discount_type == DiscountType::EntityLevel && !lines_total.is_zero() {
+            for (share, line) in shares.iter_mut().zip(&self.line_items) {
+                *share = Money::new(
+                    self.discount_total.amount() * line.item_total.amount() / lines_total.amount(),
+                );
+            }
+            settle(&self.discount_total, &mut shares);
+        }
+
+        let mut lines: Vec<Breakdown> = self
+            .line_items
+            .iter()
+            .zip(shares)
+            .map(|(line, share)| self.line_breakdown(line, share))
+            .collect();
+
+        if self.is_inclusive_tax {
+            settle_field(&mut lines, |line| &mut line.revenue);
+            settle_field(&mut lines, |line| &mut line.discount);
+            settle_field(&mut lines, |line| &mut line.tax);
+            for line in &mut lines {
+                line.profit = line.profit_from_cost();
+            }
+        }
+
+        lines
+    }
+
+    /// `share` is the line's part of the invoice-level discount, as charged.
+    fn line_breakdown(&self, line: &LineItem, share: Money) -> Breakdown {
+        let hundred = BigDecimal::from(100);
+        // multiply before dividing, so 110 inclusive of 10% tax is exactly 100
+        let exclusive = |amount: Money| {
+            if self.is_inclusive_tax {
+                Money::new(amount.amount() * &hundred / (&hundred + &line.tax_percentage))
+            } else {
+                amount
+            }
+        };
+
+        let gross = &line.rate * &line.quantity;
+        let revenue = exclusive(gross.clone());
+        let share = exclusive(share);
+        let discount = exclusive(&gross - &line.item_total) + &share;
+
+        let taxable = &revenue - &discount;
+        let taxable = if self.is_discount_before_tax {
+            taxable
+        } else {
+            taxable + share
+        };
+        let tax = Money::new(taxable.amount() * &line.tax_percentage / &hundred);
+
+        let cost = line
+            .purchase_rate
+            .as_ref()
+            .map(|purchase_rate| purchase_rate * &line.quantity);
+
+        let mut breakdown = Breakdown {
+            revenue,
+            discount,
+            tax,
+            shipping: Money::zero(),
+            adjustment: Money::zero(),
+            cost,
+            profit: None,
+        };
+        breakdown.profit = breakdown.profit_from_cost();
+        breakdown
+    }
+
+    /// The line breakdowns added up, plus shipping and adjustments.
+    pub fn breakdown(&self) -> Breakdown {
+        let lines = self.line_breakdowns();
+
+        let cost: Option<Money> = lines.iter().map(|line| line.cost.clone()).sum();
+        let profit = lines.iter().map(|line| line.profit.clone()).sum();
+
+        Breakdown {
+            revenue: lines.iter().map(|line| &line.revenue).sum(),
+            discount: lines.iter().map(|line| &line.discount).sum(),
+            tax: lines.iter().map(|line| &line.tax).sum(),
+            shipping: self.shipping_charge.clone(),
+            adjustment: self.adjustment.clone(),
+            cost,
+            profit,
+        }
+    }
+}
+
+impl Breakdown {
+    fn profit_from_cost(&self) -> Option<Money> {
+        let cost = self.cost.as_ref()?;
+        Some(&(&self.revenue - &self.discount) - cost)
+    }
+}
+
+/// Rounds every amount but the last to cents and gives the last what is left
+/// of `total`, so the rounded amounts add up to it exactly.
+fn settle(total: &Money, amounts: &mut [Money]) {
+    let Some((last, rest)) = amounts.split_last_mut() else {
+        return;
+    };
+
+    let mut left = total.clone();
+    for amount in rest {
+        *amount = Money::new(amount.rounded());
+        left = &left - &*amount;
+    }
+    *last = left;
+}
+
+/// Settles one amount of every line against their sum rounded to cents.
+fn settle_field(lines: &mut [Breakdown], field: impl Fn(&mut Breakdown) -> &mut Money) {
+    let mut amounts: Vec<Money> = lines.iter_mut().map(|line| field(line).clone()).collect();
+    let total = Money::new(amounts.iter().sum::<Money>().rounded());
+
+    settle(&total, &mut amounts);
+    for (line, amount) in lines.iter_mut().zip(amounts) {
+        *field(line) = amount;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Result;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    fn invoice(extra: serde_json::Value) -> Result<Invoice> {
+        let mut value = serde_json::json!({
+            "invoice_id": "1",
+            "invoice_number": "INV-000001",
+            "status": "sent",
+            "created_time": "2024-05-27T19:26:32+0800",
+            "customer_id": "10",
+            "customer_name": "customer",
+            "date": "2024-05-27",
+            "total": 0,
+            "line_items": [
+                { "line_item_id": "1", "name": "a", "rate": 10.0, "quantity": 10.0,
+                  "item_total": 90.0, "purchase_rate": 6.0, "tax_percentage": 10 },
+                { "line_item_id": "2", "name": "b", "rate": 30.0, "quantity": 1.0,
+                  "item_total": 30.0, "purchase_rate": 20.0, "tax_percentage": 10 }
+            ]
+        });
+        for (key, extra) in extra.as_object().unwrap() {
+            value[key] = extra.clone();
+        }
+        Ok(Invoice::try_from(value)?)
+    }
+
+    #[test]
+    fn line_discounts_reduce_profit() -> Result<()> {
+        let invoice = invoice(serde_json::json!({}))?;
+        let lines = invoice.line_breakdowns();
+
+        assert_eq!(lines[0].revenue, money("100"));
+        assert_eq!(lines[0].discount, money("10"));
+        assert_eq!(lines[0].tax, money("9"));
+        assert_eq!(lines[0].cost, Some(money("60")));
+        assert_eq!(lines[0].profit, Some(money("30")));
+        assert_eq!(invoice.profit(), Some(money("40")));
+        Ok(())
+    }
+
+    #[test]
+    fn entity_discount_is_split_over_lines() -> Result<()> {
+        let invoice = invoice(serde_json::json!({
+            "discount_type": "entity_level",
+            "discount_total": 12.0,
+            "shipping_charge": 5.0,
+            "adjustment": -0.5,
+        }))?;
+        let lines = invoice.line_breakdowns();
+
+        // 12 over line totals of 90 and 30
+        assert_eq!(lines[0].discount, money("19"));
+        assert_eq!(lines[1].discount, money("3"));
+        assert_eq!(lines[0].tax, money("8.1"));
+
+        let breakdown = invoice.breakdown();
+        assert_eq!(breakdown.discount, money("22"));
+        assert_eq!(breakdown.shipping, money("5"));
+        assert_eq!(breakdown.adjustment, money("-0.5"));
+        // shipping and adjustments are not profit
+        assert_eq!(breakdown.profit, Some(money("28")));
+        Ok(())
+    }
+
+    #[test]
+    fn entity_discount_remainder_goes_to_the_last_line() -> Result<()> {
+        let invoice = invoice(serde_json::json!({
+            "discount_type": "entity_level",
+            "discount_total": 10.0,
+            "line_items": [
+                { "line_item_id": "1", "name": "a", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0 },
+                { "line_item_id": "2", "name": "b", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0 },
+                { "line_item_id": "3", "name": "c", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0 }
+            ]
+        }))?;
+        let lines = invoice.line_breakdowns();
+
+        assert_eq!(lines[0].discount, money("3.33"));
+        assert_eq!(lines[1].discount, money("3.33"));
+        assert_eq!(lines[2].discount, money("3.34"));
+        assert_eq!(invoice.breakdown().discount, money("10"));
+        Ok(())
+    }
+
+    #[test]
+    fn inclusive_tax_remainder_goes_to_the_last_line() -> Result<()> {
+        let invoice = invoice(serde_json::json!({
+            "is_inclusive_tax": true,
+            "line_items": [
+                { "line_item_id": "1", "name": "a", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0, "tax_percentage": 6 },
+                { "line_item_id": "2", "name": "b", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0, "tax_percentage": 6 },
+                { "line_item_id": "3", "name": "c", "rate": 10.0, "quantity": 1.0,
+                  "item_total": 10.0, "purchase_rate": 5.0, "tax_percentage": 6 }
+            ]
+        }))?;
+        let lines = invoice.line_breakdowns();
+
+        // 10 inclusive of 6% is 9.4339..., three of them 28.3018...
+        assert_eq!(lines[0].revenue, money("9.43"));
+        assert_eq!(lines[1].revenue, money("9.43"));
+        assert_eq!(lines[2].revenue, money("9.44"));
+        assert_eq!(lines[2].profit, Some(money("4.44")));
+
+        let breakdown = invoice.breakdown();
+        assert_eq!(breakdown.revenue, money("28.30"));
+        assert_eq!(breakdown.tax, money("1.70"));
+        Ok(())
+    }
+
+    #[test]
+    fn inclusive_tax_is_backed_out() -> Result<()> {
+        let invoice = invoice(serde_json::json!({
+            "is_inclusive_tax": true,
+            "line_items": [
+                { "line_item_id": "1", "name": "a", "rate": 11.0, "quantity": 10.0,
+                  "item_total": 110.0, "purchase_rate": 6.0, "tax_percentage": 10 }
+            ]
+        }))?;
+        let breakdown = invoice.breakdown();
+
+        assert_eq!(breakdown.revenue, money("100"));
+        assert_eq!(breakdown.tax, money("10"));
+        assert_eq!(breakdown.profit, Some(money("40")));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_cost_leaves_profit_unknown() -> Result<()> {
+        let mut invoice = invoice(serde_json::json!({ "shipping_charge": 5.0 }))?;
+        invoice.line_items[1].purchase_rate = None;
+        let breakdown = invoice.breakdown();
+
+        assert_eq!(breakdown.cost, None);
+        assert_eq!(breakdown.profit, None);
+        assert_eq!(breakdown.shipping, money("5"));
+        Ok(())
+    }
+}
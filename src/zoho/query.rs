@@ -1,24 +1,211 @@
-use chrono::NaiveDate;
-use serde::Serialize;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Serialize, Serializer};
 
+use super::ids::{CustomerId, SalespersonId};
+use super::invoice::InvoiceStatus;
 use crate::error::{Error, Result};
 
+/// Parameters of the Zoho Books invoice listing. Fields left as `None` are not sent.
 #[derive(Serialize, Debug, Clone)]
 pub struct Query<'a> {
     pub organization_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_start: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_end: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_id: Option<&'a CustomerId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salesperson_id: Option<&'a SalespersonId>,
+    /// Only invoices modified at or after this instant.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "zoho_datetime"
+    )]
+    pub last_modified_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_column: Option<SortColumn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<SortOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
+/// The statuses the Zoho Books listing can be narrowed down to.
+#[derive(Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusFilter {
+    Sent,
+    Draft,
+    Overdue,
+    Paid,
+    Void,
+    Unpaid,
+    PartiallyPaid,
+    Viewed,
+}
+
+impl StatusFilter {
+    /// The invoice statuses the filter lets through. Most match one status,
+    /// `unpaid` covers every issued invoice with a balance left.
+    pub fn statuses(self) -> &'static [InvoiceStatus] {
+        match self {
+            StatusFilter::Sent => &[InvoiceStatus::Sent],
+            StatusFilter::Draft => &[InvoiceStatus::Draft],
+            StatusFilter::Overdue => &[InvoiceStatus::Overdue],
+            StatusFilter::Paid => &[InvoiceStatus::Paid],
+            StatusFilter::Void => &[InvoiceStatus::Void],
+            StatusFilter::Unpaid => &[
+                InvoiceStatus::Sent,
+                InvoiceStatus::Viewed,
+                InvoiceStatus::Overdue,
+                InvoiceStatus::Unpaid,
+                InvoiceStatus::PartiallyPaid,
+            ],
+            StatusFilter::PartiallyPaid => &[InvoiceStatus::PartiallyPaid],
+            StatusFilter::Viewed => &[InvoiceStatus::Viewed],
+        }
+    }
+}
+
+#[derive(Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SortColumn {
+    CustomerName,
+    InvoiceNumber,
+    Date,
+    DueDate,
+    Total,
+    Balance,
+    CreatedTime,
+    LastModifiedTime,
+}
+
+/// Zoho spells these `A` and `D`.
+#[derive(Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[serde(rename(serialize = "A"))]
+    Ascending,
+    #[serde(rename(serialize = "D"))]
+    Descending,
+}
+
+/// A `Query` that owns its parameters, so it can outlive the request it came
+/// from, e.g. as a cache key or in a background task.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedQuery {
+    organization_id: String,
+    date: Option<NaiveDate>,
+    date_start: Option<NaiveDate>,
+    date_end: Option<NaiveDate>,
+    status: Option<StatusFilter>,
+    customer_id: Option<CustomerId>,
+    salesperson_id: Option<SalespersonId>,
+    last_modified_time: Option<DateTime<Utc>>,
+    search_text: Option<String>,
+    sort_column: Option<SortColumn>,
+    sort_order: Option<SortOrder>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+impl OwnedQuery {
+    pub fn organization_id(&self) -> &str {
+        &self.organization_id
+    }
+
+    pub fn as_query(&self) -> Query<'_> {
+        Query {
+            organization_id: &self.organization_id,
+            date: self.date,
+            date_start: self.date_start,
+            date_end: self.date_end,
+            status: self.status,
+            customer_id: self.customer_id.as_ref(),
+            salesperson_id: self.salesperson_id.as_ref(),
+            last_modified_time: self.last_modified_time,
+            search_text: self.search_text.as_deref(),
+            sort_column: self.sort_column,
+            sort_order: self.sort_order,
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
+
+impl From<&Query<'_>> for OwnedQuery {
+    fn from(query: &Query<'_>) -> Self {
+        Self {
+            organization_id: query.organization_id.to_string(),
+            date: query.date,
+            date_start: query.date_start,
+            date_end: query.date_end,
+            status: query.status,
+            customer_id: query.customer_id.cloned(),
+            salesperson_id: query.salesperson_id.cloned(),
+            last_modified_time: query.last_modified_time,
+            search_text: query.search_text.map(str::to_string),
+            sort_column: query.sort_column,
+            sort_order: query.sort_order,
+            page: query.page,
+            per_page: query.per_page,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct QueryBuilder<'a> {
     organization_id: Option<&'a str>,
+    default_organization_id: Option<&'a str>,
     date: Option<NaiveDate>,
+    date_start: Option<NaiveDate>,
+    date_end: Option<NaiveDate>,
+    status: Option<StatusFilter>,
+    customer_id: Option<&'a CustomerId>,
+    salesperson_id: Option<&'a SalespersonId>,
+    last_modified_time: Option<DateTime<Utc>>,
+    search_text: Option<&'a str>,
+    sort_column: Option<SortColumn>,
+    sort_order: Option<SortOrder>,
+    page: Option<u32>,
+    per_page: Option<u32>,
 }
 
 impl<'a> Query<'a> {
     pub fn builder() -> QueryBuilder<'a> {
         QueryBuilder::default()
     }
+
+    /// Returns a copy of the query pointing at the given page.
+    pub fn with_page(&self, page: u32) -> Self {
+        Self {
+            page: Some(page),
+            ..self.clone()
+        }
+    }
+
+    /// The last business date the query can match, `None` when it is open ended.
+    pub fn last_date(&self) -> Option<NaiveDate> {
+        self.date.or(self.date_end)
+    }
+
+    /// Just the organization, for requests that are not listings.
+    pub fn organization(&self) -> Self {
+        QueryBuilder::default().build_unchecked(self.organization_id)
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?)
 }
 
 impl<'a> QueryBuilder<'a> {
@@ -27,20 +214,211 @@ impl<'a> QueryBuilder<'a> {
         self
     }
 
-    pub fn date(mut self, date: &str) -> Result<Self> {
-        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+    /// Used when no `organization_id` is given.
+    pub fn default_organization_id(mut self, organization_id: Option<&'a str>) -> Self {
+        self.default_organization_id = organization_id;
+        self
+    }
+
+    /// Parses a business date like `2024-05-27`.
+    pub fn date(self, date: &str) -> Result<Self> {
+        Ok(self.day(parse_date(date)?))
+    }
+
+    pub fn day(mut self, date: NaiveDate) -> Self {
         self.date = Some(date);
+        self
+    }
+
+    /// First business date of a range, inclusive.
+    pub fn date_start(mut self, date: &str) -> Result<Self> {
+        self.date_start = Some(parse_date(date)?);
         Ok(self)
     }
 
+    /// Last business date of a range, inclusive.
+    pub fn date_end(mut self, date: &str) -> Result<Self> {
+        self.date_end = Some(parse_date(date)?);
+        Ok(self)
+    }
+
+    pub fn status(mut self, status: StatusFilter) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn customer_id(mut self, customer_id: &'a CustomerId) -> Self {
+        self.customer_id = Some(customer_id);
+        self
+    }
+
+    pub fn salesperson_id(mut self, salesperson_id: &'a SalespersonId) -> Self {
+        self.salesperson_id = Some(salesperson_id);
+        self
+    }
+
+    pub fn last_modified_time(mut self, since: DateTime<Utc>) -> Self {
+        self.last_modified_time = Some(since);
+        self
+    }
+
+    /// Free text matched by Zoho against invoice numbers, customers and references.
+    pub fn search_text(mut self, text: &'a str) -> Self {
+        self.search_text = Some(text);
+        self
+    }
+
+    pub fn sort(mut self, column: SortColumn, order: SortOrder) -> Self {
+        self.sort_column = Some(column);
+        self.sort_order = Some(order);
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Zoho Books caps `per_page` at 200.
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = Some(per_page);
+        self
+    }
+
     pub fn build(self) -> Result<Query<'a>> {
-        if let Some(organization_id) = self.organization_id {
-            Ok(Query {
-                organization_id,
-                date: self.date,
-            })
-        } else {
-            Err(Error::custom("Missing organization_id"))
+        let Some(organization_id) = self.organization_id.or(self.default_organization_id) else {
+            return Err(Error::BadRequest("Missing organization_id".to_string()));
+        };
+
+        if self.date.is_some() && (self.date_start.is_some() || self.date_end.is_some()) {
+            return Err(Error::BadRequest(
+                "Use either date or date_start and date_end, not both".to_string(),
+            ));
         }
+        if let (Some(start), Some(end)) = (self.date_start, self.date_end) {
+            if start > end {
+                return Err(Error::BadRequest(
+                    "date_start must not be after date_end".to_string(),
+                ));
+            }
+        }
+
+        Ok(self.build_unchecked(organization_id))
+    }
+
+    fn build_unchecked(self, organization_id: &'a str) -> Query<'a> {
+        Query {
+            organization_id,
+            date: self.date,
+            date_start: self.date_start,
+            date_end: self.date_end,
+            status: self.status,
+            customer_id: self.customer_id,
+            salesperson_id: self.salesperson_id,
+            last_modified_time: self.last_modified_time,
+            search_text: self.search_text,
+            sort_column: self.sort_column,
+            sort_order: self.sort_order,
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
+
+/// Zoho expects timestamps like `2024-05-27T19:26:32+0000`.
+fn zoho_datetime<S>(
+    datetime: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match datetime {
+        Some(datetime) => serializer.collect_str(&datetime.format("%Y-%m-%dT%H:%M:%S%z")),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_map_to_zoho_parameters() -> Result<()> {
+        let customer = CustomerId::from("10");
+        let query = Query::builder()
+            .organization_id("1")
+            .date_start("2024-05-01")?
+            .date_end("2024-05-31")?
+            .status(StatusFilter::PartiallyPaid)
+            .customer_id(&customer)
+            .last_modified_time("2024-05-27T11:26:32Z".parse().unwrap())
+            .sort(SortColumn::Total, SortOrder::Descending)
+            .build()?;
+
+        assert_eq!(
+            serde_json::to_value(&query)?,
+            serde_json::json!({
+                "organization_id": "1",
+                "date_start": "2024-05-01",
+                "date_end": "2024-05-31",
+                "status": "partially_paid",
+                "customer_id": "10",
+                "last_modified_time": "2024-05-27T11:26:32+0000",
+                "sort_column": "total",
+                "sort_order": "D",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(query.organization())?,
+            serde_json::json!({ "organization_id": "1" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn falls_back_to_the_default_organization() -> Result<()> {
+        let fallback = Query::builder()
+            .default_organization_id(Some("2"))
+            .build()?;
+        let given = Query::builder()
+            .organization_id("1")
+            .default_organization_id(Some("2"))
+            .build()?;
+        let neither = Query::builder().default_organization_id(None).build();
+
+        assert_eq!(fallback.organization_id, "2");
+        assert_eq!(given.organization_id, "1");
+        assert!(matches!(neither, Err(Error::BadRequest(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_contradicting_dates() -> Result<()> {
+        let both = Query::builder()
+            .organization_id("1")
+            .date("2024-05-27")?
+            .date_end("2024-05-31")?
+            .build();
+        let backwards = Query::builder()
+            .organization_id("1")
+            .date_start("2024-05-31")?
+            .date_end("2024-05-01")?
+            .build();
+
+        assert!(matches!(both, Err(Error::BadRequest(_))));
+        assert!(matches!(backwards, Err(Error::BadRequest(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn unpaid_covers_every_open_status() {
+        let statuses = StatusFilter::Unpaid.statuses();
+
+        assert!(statuses.contains(&InvoiceStatus::Overdue));
+        assert!(statuses.contains(&InvoiceStatus::PartiallyPaid));
+        assert!(!statuses.contains(&InvoiceStatus::Paid));
+        assert!(!statuses.contains(&InvoiceStatus::Draft));
+        assert_eq!(StatusFilter::Viewed.statuses(), [InvoiceStatus::Viewed]);
     }
 }
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+/// The Zoho data centre an organization is hosted in.
+///
+/// Each region has its own accounts server and API domain; tokens issued by
+/// one region are not accepted by another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    #[serde(alias = "us")]
+    Com,
+    Eu,
+    In,
+    #[serde(rename = "com.au", alias = "au")]
+    Au,
+    Jp,
+    Ca,
+    #[serde(rename = "com.cn", alias = "cn")]
+    Cn,
+    Sa,
+}
+
+impl Region {
+    pub fn accounts_url(&self) -> &'static str {
+        match self {
+            Region::Com => "https://accounts.zoho.com",
+            Region::Eu => "https://accounts.zoho.eu",
+            Region::In => "https://accounts.zoho.in",
+            Region::Au => "https://accounts.zoho.com.au",
+            Region::Jp => "https://accounts.zoho.jp",
+            Region::Ca => "https://accounts.zohocloud.ca",
+            Region::Cn => "https://accounts.zoho.com.cn",
+            Region::Sa => "https://accounts.zoho.sa",
+        }
+    }
+
+    /// Used when a token does not carry the `api_domain` Zoho hands back.
+    pub fn api_domain(&self) -> &'static str {
+        match self {
+            Region::Com => "https://www.zohoapis.com",
+            Region::Eu => "https://www.zohoapis.eu",
+            Region::In => "https://www.zohoapis.in",
+            Region::Au => "https://www.zohoapis.com.au",
+            Region::Jp => "https://www.zohoapis.jp",
+            Region::Ca => "https://www.zohoapis.ca",
+            Region::Cn => "https://www.zohoapis.com.cn",
+            Region::Sa => "https://www.zohoapis.sa",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn region_from_config_value() {
+        let region: Region = serde_json::from_str(r#""com.au""#).unwrap();
+        assert_eq!(region, Region::Au);
+        assert_eq!(region.accounts_url(), "https://accounts.zoho.com.au");
+
+        let region: Region = serde_json::from_str(r#""eu""#).unwrap();
+        assert_eq!(region.api_domain(), "https://www.zohoapis.eu");
+    }
+}
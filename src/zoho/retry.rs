@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{field, instrument, Span};
+
+use crate::config;
+use crate::zoho::{Error, Result};
+
+/// Exponential backoff with full jitter for requests that are safe to repeat.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl From<&config::Retry> for RetryPolicy {
+    fn from(config: &config::Retry) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after the given (zero-based) attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = exp.as_millis() as u64;
+
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+
+    /// The backoff, or Zoho's `Retry-After` when longer, but never more than
+    /// `max_delay` so a bogus header cannot stall the request for hours.
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self.backoff(attempt);
+
+        retry_after
+            .map_or(backoff, |after| after.max(backoff))
+            .min(self.max_delay)
+    }
+
+    fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    fn retry_after(response: &Response) -> Option<Duration> {
+        response
+            .headers()
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .map(Duration::from_secs)
+    }
+
+    /// Sends the request, retrying transport errors, 429s and 5xxs.
+    ///
+    /// Once retries are exhausted the last response is returned as-is so the
+    /// caller can surface Zoho's error message.
+    #[instrument(
+        name = "zoho_request",
+        skip_all,
+        fields(attempts = field::Empty, status = field::Empty)
+    )]
+    pub async fn send(&self, limiter: &RateLimiter, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            let this = request
+                .try_clone()
+                .ok_or(Error::custom("Request body cannot be retried"))?;
+
+            limiter.acquire().await;
+            let result = this.send().await;
+            Span::current().record("attempts", attempt + 1);
+
+            let delay = match &result {
+                Ok(res) if Self::is_retryable(res.status()) => {
+                    self.delay(attempt, Self::retry_after(res))
+                }
+                Err(err) if err.is_timeout() || err.is_connect() => self.backoff(attempt),
+                Ok(res) => {
+                    Span::current().record("status", res.status().as_u16());
+                    return result.map_err(Error::from);
+                }
+                Err(_) => return result.map_err(Error::from),
+            };
+
+            if attempt >= self.max_retries {
+                tracing::error!("<-- Zoho: giving up after {} attempts", attempt + 1);
+                if let Ok(res) = &result {
+                    Span::current().record("status", res.status().as_u16());
+                }
+                return result.map_err(Error::from);
+            }
+
+            match &result {
+                Ok(res) => tracing::warn!(
+                    status = res.status().as_u16(),
+                    delay_ms = delay.as_millis() as u64,
+                    "<-- Zoho: retrying"
+                ),
+                Err(err) => tracing::warn!(
+                    error = %err,
+                    delay_ms = delay.as_millis() as u64,
+                    "<-- Zoho: retrying"
+                ),
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Token bucket shared by every clone of the client.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests: u32) -> Self {
+        let capacity = requests.max(1) as f64;
+
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    ///
+    /// The token is taken right away, leaving the bucket in debt when it is
+    /// empty, so each waiter sleeps its own turn without holding the lock.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().await;
+
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.capacity);
+            bucket.last = now;
+            bucket.tokens -= 1.0;
+
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / self.per_second))
+        };
+
+        if let Some(wait) = wait {
+            tracing::debug!("rate limited, waiting {}ms", wait.as_millis());
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+
+        for attempt in 0..10 {
+            assert!(policy.backoff(attempt) <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+
+        let delay = policy.delay(0, Some(Duration::from_secs(3600)));
+
+        assert_eq!(delay, Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_waits_without_the_lock() {
+        let limiter = RateLimiter::per_minute(1);
+        limiter.acquire().await;
+
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert!(!waiter.is_finished());
+        assert!(limiter.bucket.try_lock().is_ok());
+        waiter.abort();
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_waits_when_empty() {
+        // 600 per minute refills one token every 100ms
+        let limiter = RateLimiter::per_minute(600);
+
+        let start = Instant::now();
+        for _ in 0..601 {
+            limiter.acquire().await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
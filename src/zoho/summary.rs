@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use bigdecimal::BigDecimal;
+use serde::Serializer;
+
+use super::invoice::Invoice;
+use super::money::{Money, SCALE};
+
+/// Key figures of a set of invoices, taken from each invoice's [`Breakdown`]
+/// so they agree with [`Invoice::profit`]. Only invoices that are sales
+/// count, see [`Invoice::is_sale`].
+///
+/// [`Breakdown`]: super::Breakdown
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Summary {
+    pub invoice_count: usize,
+    /// Distinct customers invoiced.
+    pub customer_count: usize,
+    /// Rate times quantity, before discounts and exclusive of tax.
+    pub gross_sales: Money,
+    pub discount: Money,
+    /// Gross sales less discounts.
+    pub net_sales: Money,
+    /// `None` when the cost of any invoice is unknown.
+    pub cost: Option<Money>,
+    /// `None` when the profit of any invoice is unknown.
+    pub profit: Option<Money>,
+    /// Profit as a percentage of net sales, `None` without net sales.
+    #[serde(serialize_with = "percentage")]
+    pub margin_percentage: Option<BigDecimal>,
+    /// Net sales per invoice, `None` without invoices.
+    pub average_order_value: Option<Money>,
+}
+
+impl Summary {
+    pub fn of<'a>(invoices: impl IntoIterator<Item = &'a Invoice>) -> Self {
+        let invoices: Vec<&Invoice> = invoices
+            .into_iter()
+            .filter(|invoice| invoice.is_sale())
+            .collect();
+        let breakdowns: Vec<_> = invoices.iter().map(|invoice| invoice.breakdown()).collect();
+
+        let gross_sales: Money = breakdowns.iter().map(|b| &b.revenue).sum();
+        let discount: Money = breakdowns.iter().map(|b| &b.discount).sum();
+        let net_sales = &gross_sales - &discount;
+        let cost = breakdowns.iter().map(|b| b.cost.clone()).sum();
+        let profit: Option<Money> = breakdowns.iter().map(|b| b.profit.clone()).sum();
+
+        let margin_percentage = profit
+            .as_ref()
+            .filter(|_| !net_sales.is_zero())
+            .map(|profit| profit.amount() * BigDecimal::from(100) / net_sales.amount());
+        let average_order_value = (!invoices.is_empty())
+            .then(|| Money::new(net_sales.amount() / BigDecimal::from(invoices.len() as u64)));
+        let customer_count = invoices
+            .iter()
+            .map(|invoice| &invoice.customer_id)
+            .collect::<HashSet<_>>()
+            .len();
+
+        Self {
+            invoice_count: invoices.len(),
+            customer_count,
+            gross_sales,
+            discount,
+            net_sales,
+            cost,
+            profit,
+            margin_percentage,
+            average_order_value,
+        }
+    }
+}
+
+/// Rounded like [`Money`], as a string such as `"25.00"`.
+fn percentage<S>(value: &Option<BigDecimal>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(value) => serializer.collect_str(&value.round(SCALE).with_scale(SCALE)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Result;
+    use crate::zoho::InvoiceBuilder;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn summary_agrees_with_invoice_profit() -> Result<()> {
+        let invoices = [
+            InvoiceBuilder::new("1")
+                .customer("10")
+                .item_total(90.0)
+                .purchase_rate(6.0)
+                .build()?,
+            InvoiceBuilder::new("2")
+                .customer("10")
+                .item_total(90.0)
+                .purchase_rate(7.0)
+                .build()?,
+            InvoiceBuilder::new("3")
+                .customer("20")
+                .item_total(90.0)
+                .purchase_rate(6.0)
+                .build()?,
+        ];
+
+        let summary = Summary::of(&invoices);
+
+        assert_eq!(summary.invoice_count, 3);
+        assert_eq!(summary.customer_count, 2);
+        assert_eq!(summary.gross_sales, money("300"));
+        assert_eq!(summary.discount, money("30"));
+        assert_eq!(summary.net_sales, money("270"));
+        assert_eq!(summary.cost, Some(money("190")));
+        assert_eq!(
+            summary.profit,
+            invoices.iter().map(Invoice::profit).sum::<Option<Money>>()
+        );
+        assert_eq!(summary.profit, Some(money("80")));
+        assert_eq!(summary.average_order_value, Some(money("90")));
+
+        let serialized = serde_json::to_value(&summary)?;
+        assert_eq!(serialized["margin_percentage"], "29.63");
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_cost_and_no_invoices_leave_figures_out() -> Result<()> {
+        let unknown = Summary::of(&[InvoiceBuilder::new("1").item_total(90.0).build()?]);
+        let empty = Summary::of(&[]);
+
+        assert_eq!(unknown.profit, None);
+        assert_eq!(unknown.margin_percentage, None);
+        assert_eq!(unknown.average_order_value, Some(money("90")));
+        assert_eq!(empty.invoice_count, 0);
+        assert_eq!(empty.margin_percentage, None);
+        assert_eq!(empty.average_order_value, None);
+        Ok(())
+    }
+
+    #[test]
+    fn figures_are_summed_exactly() -> Result<()> {
+        let mut invoice = InvoiceBuilder::new("1").build()?;
+        invoice.line_items[0].rate = money("0.1");
+        invoice.line_items[0].quantity = BigDecimal::from(3);
+        invoice.line_items[0].purchase_rate = Some(money("0.07"));
+        invoice.line_items[0].item_total = money("0.3");
+
+        let invoices: Vec<_> = std::iter::repeat_n(invoice, 10).collect();
+        let summary = Summary::of(&invoices);
+
+        assert_eq!(summary.net_sales, Money::from(3));
+        assert_eq!(summary.profit, Some(money("0.9")));
+        assert_eq!(serde_json::to_value(&summary)?["profit"], "0.90");
+        Ok(())
+    }
+}
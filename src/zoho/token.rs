@@ -1,6 +1,6 @@
-use secrecy::{ExposeSecret, Secret};
-use serde::{ser::SerializeStruct, Serialize};
-use sqlx::{postgres::PgRow, FromRow, Row};
+use secrecy::Secret;
+
+use super::error::{from_value, Error, Result};
 
 #[derive(Debug)]
 pub struct Token {
@@ -15,64 +15,60 @@ pub struct Token {
 
 impl Token {
     pub fn is_expired(&self) -> bool {
-        self.time_stamp + chrono::Duration::seconds(self.expires_in) < chrono::Utc::now()
+        self.expires_within(chrono::Duration::zero())
+    }
+
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.time_stamp + chrono::Duration::seconds(self.expires_in)
     }
-}
 
-impl Serialize for Token {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let mut state = serializer.serialize_struct("Token", 6)?;
-        state.serialize_field("access_token", &self.access_token.expose_secret())?;
-        state.serialize_field("api_domain", &self.api_domain)?;
-        state.serialize_field("expires_in", &self.expires_in)?;
-        state.serialize_field(
-            "refresh_token",
-            &self.refresh_token.as_ref().map(|rt| rt.expose_secret()),
-        )?;
-        state.serialize_field("scope", &self.scope)?;
-        state.serialize_field("token_type", &self.token_type)?;
-        state.serialize_field("time_stamp", &self.time_stamp)?;
-        state.end()
+    /// Whether the token expires in less than `margin` from now.
+    pub fn expires_within(&self, margin: chrono::Duration) -> bool {
+        self.expires_at() < chrono::Utc::now() + margin
     }
 }
 
-impl<'r> FromRow<'r, PgRow> for Token {
-    fn from_row(row: &'r PgRow) -> std::result::Result<Self, sqlx::Error> {
-        Ok(Token {
-            access_token: Secret::new(row.try_get("access_token")?),
-            api_domain: row.try_get("api_domain")?,
-            expires_in: row.try_get("expires_in")?,
-            refresh_token: row
-                .try_get("refresh_token")
-                .map(|opt: Option<String>| opt.map(Secret::new))?,
-            scope: row.try_get("scope")?,
-            token_type: row.try_get("token_type")?,
-            time_stamp: row.try_get("time_stamp")?,
+/// The body of a successful token request; refreshes leave out `refresh_token`.
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    api_domain: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+    scope: String,
+    token_type: String,
+}
+
+impl TryFrom<serde_json::Value> for Token {
+    type Error = Error;
+
+    fn try_from(val: serde_json::Value) -> Result<Self> {
+        let res: TokenResponse = from_value(val)?;
+
+        Ok(Self {
+            access_token: res.access_token.into(),
+            api_domain: res.api_domain,
+            expires_in: res.expires_in,
+            refresh_token: res.refresh_token.map(|s| s.into()),
+            scope: res.scope,
+            token_type: res.token_type,
+            time_stamp: chrono::Utc::now(),
         })
     }
 }
 
-impl From<serde_json::Value> for Token {
-    fn from(val: serde_json::Value) -> Self {
-        let now = chrono::Utc::now();
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        let refresh_token = if val["refresh_token"].is_null() {
-            None
-        } else {
-            Some(val["refresh_token"].as_str().unwrap().to_string())
-        };
+    #[test]
+    fn token_without_access_token_is_an_error() {
+        let value =
+            serde_json::json!({ "api_domain": "https://www.zohoapis.com", "expires_in": 3600 });
 
-        Self {
-            access_token: val["access_token"].as_str().unwrap().to_string().into(),
-            api_domain: val["api_domain"].as_str().unwrap().to_string(),
-            expires_in: val["expires_in"].as_i64().unwrap(),
-            refresh_token: refresh_token.map(|s| s.into()),
-            scope: val["scope"].as_str().unwrap().to_string(),
-            token_type: val["token_type"].as_str().unwrap().to_string(),
-            time_stamp: now,
+        match Token::try_from(value) {
+            Err(Error::Parse { path, .. }) => assert_eq!(path, "."),
+            other => panic!("expected a parse error, got {other:?}"),
         }
     }
 }
@@ -0,0 +1,24 @@
+// Sign in and go back to the dashboard, which uses the session cookie set by /auth/login
+document.getElementById('login-form').addEventListener('submit', async (event) => {
+    event.preventDefault();
+
+    const flash = document.getElementById('flash');
+    flash.className = '';
+    flash.textContent = '';
+
+    const response = await fetch('/auth/login', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+            email: document.getElementById('email').value,
+            password: document.getElementById('password').value,
+        }),
+    });
+
+    if (response.ok) {
+        window.location.href = '/';
+    } else {
+        flash.classList.add('flash-error');
+        flash.textContent = 'Wrong email or password.';
+    }
+});
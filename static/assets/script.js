@@ -29,14 +29,29 @@ async function fetchAndDisplayInvoices() {
 
         // Format the selected date to match the format required by the API
         const formattedDate = moment(selectedDate, 'D MMM YYYY').format('YYYY-MM-DD');
-        const url = `https://delivr.onrender.com/invoices?organization_id=820117212&date=${formattedDate}`;
+        // Without a picked organization the server uses the tenant's default
+        const organizationId = document.getElementById('organization-picker').value;
+        const url = organizationId
+            ? `/invoices?organization_id=${organizationId}&date=${formattedDate}`
+            : `/invoices?date=${formattedDate}`;
 
         const response = await fetch(url);
+        if (response.status === 401) {
+            window.location.href = '/login.html';
+            return;
+        }
         const data = await response.json();
+        if (!response.ok) {
+            // errors come back as { code, message, request_id }
+            showError(data.message);
+            hideLoadingAnimation();
+            displayInvoices([]);
+            return;
+        }
 
         hideLoadingAnimation();
 
-        displayInvoices(data);
+        displayInvoices(data.invoices, data);
     } catch (error) {
         hideLoadingAnimation();
         displayInvoices([]);
@@ -44,8 +59,23 @@ async function fetchAndDisplayInvoices() {
     }
 }
 
-// Function to display the data
-function displayInvoices(invoices) {
+// Function to show an error message from the API
+function showError(message) {
+    const flash = document.getElementById('flash');
+    flash.className = 'flash-error';
+    flash.textContent = message || 'Something went wrong, please try again.';
+}
+
+// Amounts arrive as exact strings rounded to two decimals, such as "120.00",
+// so they are shown as they are instead of being added up as floats here.
+// Profit is left out of the response for roles that may not see it,
+// and is null when Zoho has no purchase rate for an item
+function formatProfit(profit) {
+    return profit == null ? '' : ` (${profit})`;
+}
+
+// Function to display the data, with the totals computed by the server
+function displayInvoices(invoices, totals) {
     const invoicesContainer = document.getElementById('invoices');
 
     invoices.forEach(invoice => {
@@ -61,7 +91,7 @@ function displayInvoices(invoices) {
             itemDiv.classList.add('line-item');
 
             const itemName = document.createElement('p');
-            itemName.textContent = `${item.name} x ${item.quantity}pcs = RM${item.item_total.toFixed(2)} (${item.item_profit.toFixed(2)})`;
+            itemName.textContent = `${item.name} x ${item.quantity}pcs = RM${item.item_total}${formatProfit(item.item_profit)}`;
             itemDiv.appendChild(itemName);
 
             invoiceDiv.appendChild(itemDiv);
@@ -69,24 +99,15 @@ function displayInvoices(invoices) {
 
         const totalDiv = document.createElement('div');
         totalDiv.classList.add('invoice-total');
-        totalDiv.textContent = `RM${invoice.total.toFixed(2)} (${invoice.profit.toFixed(2)})`;
+        totalDiv.textContent = `RM${invoice.total}${formatProfit(invoice.profit)}`;
         invoiceDiv.appendChild(totalDiv);
 
         invoicesContainer.appendChild(invoiceDiv);
     });
 
-
-    // Calculate total sales and profit
-    let totalSales = 0;
-    let totalProfit = 0;
-
-    invoices.forEach(invoice => {
-        console.log(invoice);
-        invoice.line_items.forEach(item => {
-            totalSales += item.item_total;
-            totalProfit += item.item_profit;
-        });
-    });
+    if (!totals) {
+        return;
+    }
 
     // Display total sales and profit
     const totalCard = document.createElement('div');
@@ -94,9 +115,21 @@ function displayInvoices(invoices) {
 
     const totalProfitElement = document.createElement('div');
     totalProfitElement.classList.add('total-sales-profit');
-    totalProfitElement.textContent = `Total: RM${totalSales.toFixed(2)} (${totalProfit.toFixed(2)})`;
+    totalProfitElement.textContent = `Total: RM${totals.sales}${formatProfit(totals.profit)}`;
     totalCard.appendChild(totalProfitElement);
 
+    // The rest of the figures come from the server's summary as well
+    const summary = totals.summary;
+    if (summary) {
+        const summaryElement = document.createElement('div');
+        summaryElement.classList.add('summary');
+        const margin = summary.margin_percentage == null ? '' : `, margin ${summary.margin_percentage}%`;
+        const average = summary.average_order_value == null ? '' : `, RM${summary.average_order_value} per order`;
+        summaryElement.textContent =
+            `${summary.invoice_count} invoices, ${summary.customer_count} customers${average}${margin}`;
+        totalCard.appendChild(summaryElement);
+    }
+
     invoicesContainer.appendChild(totalCard);
 }
 
@@ -126,12 +159,64 @@ function initializeDatePicker() {
     });
 }
 
+// Function to fill the organization picker with the organizations of the tenant,
+// keeping the last one picked in this browser selected
+async function loadOrganizations() {
+    const picker = document.getElementById('organization-picker');
+    const response = await fetch('/organizations');
+    if (response.status === 401) {
+        window.location.href = '/login.html';
+        return;
+    }
+    const data = await response.json();
+    if (!response.ok) {
+        showError(data.message);
+        return;
+    }
+
+    const remembered = localStorage.getItem('organization_id');
+    data.forEach(organization => {
+        const option = document.createElement('option');
+        option.value = organization.organization_id;
+        option.textContent = organization.name;
+        option.selected = remembered
+            ? organization.organization_id === remembered
+            : organization.is_default_org;
+        picker.appendChild(option);
+    });
+
+    picker.addEventListener('change', () => {
+        localStorage.setItem('organization_id', picker.value);
+        fetchAndDisplayInvoices();
+    });
+}
+
+// Function to show the outcome of connecting Zoho, passed back by /oauth/callback
+function showOAuthResult() {
+    const params = new URLSearchParams(window.location.search);
+    const result = params.get('oauth');
+    if (!result) {
+        return;
+    }
+
+    const flash = document.getElementById('flash');
+    flash.classList.add(result === 'success' ? 'flash-success' : 'flash-error');
+    flash.textContent = result === 'success'
+        ? 'Zoho Books connected.'
+        : 'Could not connect Zoho Books, please try again.';
+
+    // Drop the parameter so a reload does not show the message again
+    window.history.replaceState({}, '', window.location.pathname);
+}
 
 // Add event listeners to buttons
 document.getElementById('left-button').addEventListener('click', () => changeDateBy(-1));
 document.getElementById('right-button').addEventListener('click', () => changeDateBy(1));
 
 // Call the function to initialize the date picker when the DOM is ready
-$(function() {
+$(async function() {
+    showOAuthResult();
     initializeDatePicker();
+    await loadOrganizations();
+    fetchAndDisplayInvoices();
 });
@@ -0,0 +1,183 @@
+use delivr::auth::Role;
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::StatusCode;
+
+use crate::error::Result;
+use crate::helpers::setup_app;
+use crate::mock_zoho::ORGANIZATION_ID;
+
+#[tokio::test]
+async fn protected_routes_require_authentication() -> Result<()> {
+    let app = setup_app().await?;
+    let client = reqwest::Client::new();
+
+    for path in [
+        "/tokens".to_string(),
+        "/status/tokens".to_string(),
+        "/oauth/authorize".to_string(),
+        "/users".to_string(),
+        format!("/invoices?organization_id={ORGANIZATION_ID}&date=2024-05-27"),
+    ] {
+        let response = client.get(format!("{}{path}", app.url())).send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{path}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn login_sets_a_session_cookie() -> Result<()> {
+    let app = setup_app().await?;
+    app.create_user("Manager@Example.com", "hunter22", Role::Manager)
+        .await?;
+
+    let client = reqwest::Client::new();
+    let login = |password: &str| {
+        client
+            .post(format!("{}/auth/login", app.url()))
+            .json(&serde_json::json!({ "email": "manager@example.com", "password": password }))
+            .send()
+    };
+
+    let response = login("wrong").await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = login("hunter22").await?;
+    assert!(response.status().is_success());
+
+    let cookie = response.headers()[SET_COOKIE].to_str()?;
+    assert!(cookie.contains("HttpOnly"));
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    let me: serde_json::Value = client
+        .get(format!("{}/auth/me", app.url()))
+        .header(COOKIE, &cookie)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(me["email"], "manager@example.com");
+    assert_eq!(me["role"], "manager");
+
+    let response = client
+        .get(format!("{}/tokens", app.url()))
+        .header(COOKIE, &cookie)
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    client
+        .post(format!("{}/auth/logout", app.url()))
+        .header(COOKIE, &cookie)
+        .send()
+        .await?;
+
+    let response = client
+        .get(format!("{}/auth/me", app.url()))
+        .header(COOKIE, &cookie)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drivers_do_not_see_profit() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let url = format!(
+        "{}/invoices?organization_id={}&date=2024-05-27",
+        app.url(),
+        ORGANIZATION_ID
+    );
+
+    let driver = app.client_as(Role::Driver).await?;
+    let body: serde_json::Value = driver.get(&url).send().await?.json().await?;
+    let invoices = &body["invoices"];
+
+    assert_eq!(invoices[0]["total"], "120.00");
+    assert!(invoices[0].get("profit").is_none());
+    assert!(invoices[0]["line_items"][0].get("item_profit").is_none());
+    assert!(invoices[0]["breakdown"].get("cost").is_none());
+    assert_eq!(invoices[0]["breakdown"]["revenue"], "120.00");
+    assert!(body.get("profit").is_none());
+    assert!(body["summary"].get("margin_percentage").is_none());
+    assert_eq!(body["summary"]["invoice_count"], 3);
+
+    let viewer = app.client_as(Role::Viewer).await?;
+    let body: serde_json::Value = viewer.get(&url).send().await?.json().await?;
+
+    assert_eq!(body["invoices"][0]["profit"], "20.00");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_admins_connect_zoho() -> Result<()> {
+    let app = setup_app().await?;
+
+    let manager = app.client_as(Role::Manager).await?;
+    let response = manager
+        .get(format!("{}/oauth/authorize", app.url()))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let driver = app.client_as(Role::Driver).await?;
+    let response = driver.get(format!("{}/tokens", app.url())).send().await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn admins_create_users_and_api_keys() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client();
+
+    let user: serde_json::Value = client
+        .post(format!("{}/users", app.url()))
+        .json(&serde_json::json!({
+            "email": "driver@example.com",
+            "password": "correct horse",
+            "role": "driver",
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(user["role"], "driver");
+
+    let created: serde_json::Value = client
+        .post(format!(
+            "{}/users/{}/api-keys",
+            app.url(),
+            user["id"].as_str().unwrap()
+        ))
+        .json(&serde_json::json!({ "name": "delivery app" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with("dlv_"));
+
+    let me: serde_json::Value = reqwest::Client::new()
+        .get(format!("{}/auth/me", app.url()))
+        .bearer_auth(key)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(me["email"], "driver@example.com");
+
+    let key_hash: String =
+        sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE name = 'delivery app'")
+            .fetch_one(&app.pool)
+            .await?;
+    assert_ne!(key_hash, key);
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use delivr::database::Organizations;
+use delivr::zoho::Organization;
+use reqwest::StatusCode;
+
+use crate::error::Result;
+use crate::helpers::setup_app;
+use crate::mock_zoho::ORGANIZATION_ID;
+
+#[tokio::test]
+async fn invalid_date_is_a_bad_request() -> Result<()> {
+    let app = setup_app().await?;
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&date=27-05-2024",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "invalid_date");
+    assert!(error["message"].is_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_parameter_is_a_bad_request() -> Result<()> {
+    let app = setup_app().await?;
+
+    // with two organizations and neither the default, there is nothing to fall back to
+    let organization = |organization_id: &str| Organization {
+        organization_id: organization_id.to_string(),
+        name: format!("Organization {organization_id}"),
+        is_default_org: false,
+        currency_code: "MYR".to_string(),
+        time_zone: None,
+    };
+    let organizations = Organizations { pool: &app.pool };
+    organizations
+        .replace_all(app.tenant_id, &[organization("1"), organization("2")])
+        .await?;
+
+    let response = app
+        .client()
+        .get(format!("{}/invoices?date=2024-05-27", app.url()))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "bad_request");
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("organization_id"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() -> Result<()> {
+    let app = setup_app().await?;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/tokens", app.url()))
+        .header("x-request-id", "dashboard-42")
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["x-request-id"], "dashboard-42");
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "unauthorized");
+    assert_eq!(error["request_id"], "dashboard-42");
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/tokens", app.url()))
+        .send()
+        .await?;
+
+    let generated = response.headers()["x-request-id"].to_str()?.to_string();
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["request_id"], generated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn duplicate_user_is_a_conflict() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client();
+    let user = serde_json::json!({
+        "email": "driver@example.com",
+        "password": "correct horse",
+        "role": "driver",
+    });
+
+    let response = client
+        .post(format!("{}/users", app.url()))
+        .json(&user)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = client
+        .post(format!("{}/users", app.url()))
+        .json(&user)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "conflict");
+
+    Ok(())
+}
@@ -1,10 +1,21 @@
 use crate::error::Result;
+use crate::mock_zoho::{MockZoho, REFRESH_TOKEN, SCOPE};
 use delivr::app;
+use delivr::auth::{generate_secret, hash_password, hash_secret, Role};
 use delivr::config::{get_config, Config};
-use sqlx::{Connection, PgConnection, Row};
+use delivr::database::{ApiKeys, Cipher, Tenants, Tokens, Users, DEFAULT_TENANT};
+use delivr::zoho::Token;
+use sqlx::{Connection, PgConnection, PgPool, Row};
 
 pub struct App {
     config: Config,
+    pub pool: PgPool,
+    pub cipher: Cipher,
+    pub zoho: MockZoho,
+    /// The `default` tenant, which the admin and seeded tokens belong to.
+    pub tenant_id: uuid::Uuid,
+    /// API key of an admin created during setup.
+    pub admin_key: String,
 }
 
 impl App {
@@ -14,6 +25,118 @@ impl App {
             self.config.application.host, self.config.application.port
         )
     }
+
+    /// A client authenticated as the admin created during setup.
+    pub fn client(&self) -> reqwest::Client {
+        self.client_builder().build().unwrap()
+    }
+
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        client_with_key(&self.admin_key)
+    }
+
+    /// A client authenticated as a new user with `role`.
+    pub async fn client_as(&self, role: Role) -> Result<reqwest::Client> {
+        let user = self
+            .create_user(&format!("{}@example.com", role.as_str()), "password", role)
+            .await?;
+        let key = create_api_key(&self.pool, user).await?;
+
+        Ok(client_with_key(&key).build()?)
+    }
+
+    pub async fn create_user(&self, email: &str, password: &str, role: Role) -> Result<uuid::Uuid> {
+        self.create_user_in(self.tenant_id, email, password, role)
+            .await
+    }
+
+    pub async fn create_user_in(
+        &self,
+        tenant_id: uuid::Uuid,
+        email: &str,
+        password: &str,
+        role: Role,
+    ) -> Result<uuid::Uuid> {
+        let password_hash = hash_password(password.to_string().into()).await?;
+        let users = Users { pool: &self.pool };
+
+        Ok(users
+            .insert(tenant_id, email, &password_hash, role)
+            .await?
+            .id)
+    }
+
+    /// A new tenant and a client authenticated as its admin.
+    pub async fn create_tenant(&self, name: &str) -> Result<(uuid::Uuid, reqwest::Client)> {
+        let tenants = Tenants { pool: &self.pool };
+        let tenant = tenants.insert(name).await?;
+        let admin = self
+            .create_user_in(
+                tenant.id,
+                &format!("admin@{name}.example.com"),
+                "password",
+                Role::Admin,
+            )
+            .await?;
+        let key = create_api_key(&self.pool, admin).await?;
+
+        Ok((tenant.id, client_with_key(&key).build()?))
+    }
+
+    /// Stores a token for the mock Zoho, as if `/token/:code` had been called.
+    pub async fn seed_token(&self, expired: bool) -> Result<()> {
+        self.insert_token(REFRESH_TOKEN, expired).await
+    }
+
+    /// Stores an expired token whose refresh token Zoho no longer accepts.
+    pub async fn seed_revoked_token(&self) -> Result<()> {
+        self.insert_token("revoked-refresh-token", true).await
+    }
+
+    async fn insert_token(&self, refresh_token: &str, expired: bool) -> Result<()> {
+        let time_stamp = if expired {
+            chrono::Utc::now() - chrono::Duration::hours(2)
+        } else {
+            chrono::Utc::now()
+        };
+
+        let token = Token {
+            access_token: "access-seeded".to_string().into(),
+            api_domain: self.zoho.url.clone(),
+            expires_in: 3600,
+            refresh_token: Some(refresh_token.to_string().into()),
+            scope: SCOPE.to_string(),
+            token_type: "Bearer".to_string(),
+            time_stamp,
+        };
+
+        let tokens = Tokens {
+            pool: &self.pool,
+            cipher: &self.cipher,
+        };
+        tokens.insert(self.tenant_id, &token).await?;
+        Ok(())
+    }
+}
+
+fn client_with_key(key: &str) -> reqwest::ClientBuilder {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::AUTHORIZATION,
+        format!("Bearer {key}").parse().unwrap(),
+    );
+
+    reqwest::Client::builder().default_headers(headers)
+}
+
+async fn create_api_key(pool: &PgPool, user_id: uuid::Uuid) -> Result<String> {
+    let key = generate_secret();
+    let api_keys = ApiKeys { pool };
+    api_keys
+        .insert(&hash_secret(&key), user_id, "tests")
+        .await?;
+
+    Ok(key)
 }
 
 async fn check_database(config: &Config) -> Result<()> {
@@ -66,17 +189,50 @@ async fn setup_database(config: &Config) -> Result<()> {
 }
 
 pub async fn setup_app() -> Result<App> {
+    setup_app_with(|_| {}).await
+}
+
+/// Like `setup_app`, with the configuration adjusted before the server starts.
+pub async fn setup_app_with(configure: impl FnOnce(&mut Config)) -> Result<App> {
     // set APP_ENVIRONMENT
     std::env::set_var("APP_ENVIRONMENT", "test");
     let mut config = get_config()?;
+    configure(&mut config);
 
     config.database.database_name = uuid::Uuid::new_v4().to_string();
     setup_database(&config).await?;
 
+    let zoho = MockZoho::start().await?;
+    config.zoho.accounts_url = Some(zoho.url.clone());
+    config.zoho.api_url = Some(zoho.url.clone());
+
     config.application.port = 0;
     let port = app::serve(&config).await?;
 
     config.application.port = port;
 
-    Ok(App { config })
+    let pool = PgPool::connect(&config.database.connection_string()).await?;
+    let cipher = Cipher::from_config(&config.encryption)?;
+
+    let tenants = Tenants { pool: &pool };
+    let tenant = tenants
+        .get_by_name(DEFAULT_TENANT)
+        .await?
+        .ok_or("The default tenant is missing")?;
+
+    // the password is never used, so skip the deliberately slow hashing
+    let users = Users { pool: &pool };
+    let admin = users
+        .insert(tenant.id, "admin@example.com", "unusable", Role::Admin)
+        .await?;
+    let admin_key = create_api_key(&pool, admin.id).await?;
+
+    Ok(App {
+        config,
+        pool,
+        cipher,
+        zoho,
+        tenant_id: tenant.id,
+        admin_key,
+    })
 }
@@ -0,0 +1,390 @@
+use std::sync::atomic::Ordering;
+
+use reqwest::StatusCode;
+
+use crate::error::Result;
+use crate::helpers::setup_app;
+use crate::mock_zoho::ORGANIZATION_ID;
+
+#[tokio::test]
+async fn invoices_by_date_returns_every_page_in_created_order() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+
+    let body: serde_json::Value = response.json().await?;
+    let ids: Vec<_> = body["invoices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|invoice| invoice["invoice_id"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(ids, ["2", "1", "3"]);
+    assert_eq!(body["sales"], "360.00");
+    assert_eq!(body["profit"], "60.00");
+    assert_eq!(app.zoho.counters.list_requests.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn summary_matches_the_invoices() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let url = |path: &str| {
+        format!(
+            "{}/{path}?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        )
+    };
+    let summary: serde_json::Value = client
+        .get(url("invoices/summary"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let invoices: serde_json::Value = client.get(url("invoices")).send().await?.json().await?;
+
+    assert_eq!(summary, invoices["summary"]);
+    assert_eq!(summary["invoice_count"], 3);
+    assert_eq!(summary["customer_count"], 1);
+    assert_eq!(summary["profit"], invoices["profit"]);
+    assert_eq!(summary["net_sales"], "360.00");
+    assert_eq!(summary["margin_percentage"], "16.67");
+    assert_eq!(summary["average_order_value"], "120.00");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn headline_figures_agree_with_the_summary_after_discounts() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+    app.zoho.discount("1", 12.0);
+
+    let body: serde_json::Value = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    assert_eq!(body["sales"], "348.00");
+    assert_eq!(body["sales"], body["summary"]["net_sales"]);
+    assert_eq!(body["profit"], "48.00");
+    assert_eq!(body["profit"], body["summary"]["profit"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn void_invoices_are_listed_but_not_counted() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+    app.zoho.void("2");
+
+    let body: serde_json::Value = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    assert_eq!(body["invoices"].as_array().map(Vec::len), Some(3));
+    assert_eq!(body["sales"], "240.00");
+    assert_eq!(body["profit"], "40.00");
+    assert_eq!(body["summary"]["invoice_count"], 2);
+    assert_eq!(body["summary"]["net_sales"], "240.00");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoices_default_to_today_in_the_business_timezone() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+
+    let today = chrono::Utc::now()
+        .with_timezone(&chrono_tz::Asia::Kuala_Lumpur)
+        .date_naive()
+        .to_string();
+    let params = app.zoho.counters.list_params.lock().unwrap().clone();
+    assert_eq!(params.get("date"), Some(&today));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoice_filters_are_passed_to_zoho() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&date_start=2024-05-01&date_end=2024-05-31\
+             &status=overdue&customer_id=10&search_text=indon&sort_column=total&sort_order=descending",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+
+    let params = app.zoho.counters.list_params.lock().unwrap().clone();
+    assert_eq!(params["date_start"], "2024-05-01");
+    assert_eq!(params["date_end"], "2024-05-31");
+    assert_eq!(params["status"], "overdue");
+    assert_eq!(params["customer_id"], "10");
+    assert_eq!(params["search_text"], "indon");
+    assert_eq!(params["sort_column"], "total");
+    assert_eq!(params["sort_order"], "D");
+    assert!(!params.contains_key("date"));
+
+    // sorted listings keep Zoho's order instead of the creation order
+    let body: serde_json::Value = response.json().await?;
+    let ids: Vec<_> = body["invoices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|invoice| invoice["invoice_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["1", "2", "3"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoice_filters_are_validated() -> Result<()> {
+    let app = setup_app().await?;
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&date_start=2024-05-31&date_end=2024-05-01",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={}&status=lost",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoices_by_date_refreshes_expired_token() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(true).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+    assert_eq!(app.zoho.counters.refreshes(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoices_by_date_without_token_fails() -> Result<()> {
+    let app = setup_app().await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "zoho_not_connected");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoices_for_unknown_organization_fails() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id=0&date=2024-05-27",
+            app.url()
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "invalid_organization");
+    assert_eq!(error["message"], "Organization 0 is not connected");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoice_by_id() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoice/1?organization_id={}",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert!(response.status().is_success());
+
+    let invoice: serde_json::Value = response.json().await?;
+    assert_eq!(invoice["invoice_id"], "1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoice_not_found_fails() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoice/404?organization_id={}",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "not_found");
+    assert_eq!(error["details"]["zoho_code"], 1002);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_requests_share_one_refresh() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(true).await?;
+
+    let client = app.client();
+    let url = format!(
+        "{}/invoices?organization_id={}&date=2024-05-27",
+        app.url(),
+        ORGANIZATION_ID
+    );
+
+    let responses = futures::future::join_all((0..5).map(|_| client.get(&url).send())).await;
+
+    for response in responses {
+        assert!(response?.status().is_success());
+    }
+    assert_eq!(app.zoho.counters.refreshes(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeated_requests_are_served_from_the_cache() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let url = format!(
+        "{}/invoices?organization_id={}&date=2024-05-27",
+        app.url(),
+        ORGANIZATION_ID
+    );
+    let list_requests = || app.zoho.counters.list_requests.load(Ordering::SeqCst);
+
+    let (first, second) = tokio::join!(client.get(&url).send(), client.get(&url).send());
+    assert!(first?.status().is_success());
+    assert!(second?.status().is_success());
+    assert_eq!(list_requests(), 2);
+
+    let response = client
+        .delete(format!("{}/cache/{}", app.url(), ORGANIZATION_ID))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client.get(&url).send().await?;
+    assert!(response.status().is_success());
+    assert_eq!(list_requests(), 4);
+
+    Ok(())
+}
@@ -1,5 +1,14 @@
 mod error;
 mod helpers;
+mod mock_zoho;
 
 // endpoints
+mod auth;
+mod errors;
 mod health;
+mod invoices;
+mod reports;
+mod status;
+mod sync;
+mod tenants;
+mod token;
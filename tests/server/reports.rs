@@ -0,0 +1,149 @@
+use delivr::auth::Role;
+use reqwest::StatusCode;
+
+use crate::error::Result;
+use crate::helpers::setup_app;
+use crate::mock_zoho::ORGANIZATION_ID;
+
+#[tokio::test]
+async fn sales_report_lists_every_day_of_the_range() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let response = app
+        .client()
+        .get(format!(
+            "{}/reports/sales?organization_id={}&from=2024-05-26&to=2024-05-28&bucket=day",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let report: serde_json::Value = response.json().await?;
+    let days: Vec<_> = report["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| {
+            (
+                bucket["start"].as_str().unwrap(),
+                bucket["invoice_count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        days,
+        [("2024-05-26", 0), ("2024-05-27", 3), ("2024-05-28", 0)]
+    );
+    assert_eq!(report["total"]["profit"], "60.00");
+    assert_eq!(report["buckets"][1]["profit"], "60.00");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sales_report_is_limited() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let too_long = app
+        .client()
+        .get(format!(
+            "{}/reports/sales?organization_id={}&from=2023-01-01&to=2024-05-28",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+    assert_eq!(too_long.status(), StatusCode::BAD_REQUEST);
+
+    let driver = app.client_as(Role::Driver).await?;
+    let response = driver
+        .get(format!(
+            "{}/reports/sales?from=2024-05-26&to=2024-05-28",
+            app.url()
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn salesperson_report_ranks_salespeople() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let report: serde_json::Value = app
+        .client()
+        .get(format!(
+            "{}/reports/salespeople?from=2024-05-01&to=2024-05-31",
+            app.url()
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    let yuki = &report["salespeople"][0];
+    assert_eq!(yuki["salesperson_name"], "Yuki");
+    assert_eq!(yuki["invoice_count"], 3);
+    assert_eq!(yuki["profit"], "60.00");
+    assert_eq!(yuki["top_customers"][0]["customer_name"], "1. indon 2");
+    assert_eq!(yuki["top_customers"][0]["net_sales"], "360.00");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn commission_follows_the_tenant_plan() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let url = format!(
+        "{}/reports/commission?from=2024-05-01&to=2024-05-31",
+        app.url()
+    );
+    let response = client.get(&url).send().await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let backwards = serde_json::json!({
+        "basis": "sales",
+        "tiers": [{ "target": "1000", "rate": "5" }, { "target": "0", "rate": "2" }],
+    });
+    let response = client
+        .put(format!("{}/commission/plan", app.url()))
+        .json(&backwards)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let plan = serde_json::json!({
+        "basis": "profit",
+        "tiers": [{ "target": "0", "rate": "10" }],
+    });
+    let response = client
+        .put(format!("{}/commission/plan", app.url()))
+        .json(&plan)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let report: serde_json::Value = client.get(&url).send().await?.json().await?;
+    let statement = &report["statements"][0];
+    assert_eq!(statement["salesperson_name"], "Yuki");
+    assert_eq!(statement["basis_amount"], "60.00");
+    assert_eq!(statement["commission"], "6.00");
+    assert_eq!(statement["invoices"].as_array().map(Vec::len), Some(3));
+
+    let viewer = app.client_as(Role::Viewer).await?;
+    let response = viewer.get(&url).send().await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use crate::error::Result;
+use crate::helpers::{setup_app, App};
+use crate::mock_zoho::{ORGANIZATION_ID, SCOPE};
+
+async fn token_status(app: &App) -> Result<serde_json::Value> {
+    let status = app
+        .client()
+        .get(format!("{}/status/tokens", app.url()))
+        .send()
+        .await?
+        .json::<serde_json::Value>()
+        .await?;
+
+    Ok(status)
+}
+
+#[tokio::test]
+async fn status_records_successful_refresh() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(true).await?;
+
+    let client = app.client();
+    client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    let status = token_status(&app).await?;
+
+    assert_eq!(status[0]["scope"], SCOPE);
+    assert_eq!(status[0]["status"], "valid");
+    assert!(!status[0]["last_refreshed_at"].is_null());
+    assert!(status[0].get("access_token").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn status_flags_revoked_token() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_revoked_token().await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "zoho_reauthorization_required");
+
+    let status = token_status(&app).await?;
+
+    assert_eq!(status[0]["status"], "reauthorization_required");
+    assert!(!status[0]["last_refresh_error"].is_null());
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use delivr::database::SyncCursors;
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::helpers::{setup_app, setup_app_with, App};
+use crate::mock_zoho::ORGANIZATION_ID;
+
+/// An app whose background sync covers the mock organization. Its first run
+/// fails for lack of a token; waiting for it keeps it out of the way of the
+/// syncs the test makes.
+async fn setup_synced_app() -> Result<App> {
+    let app = setup_app_with(|config| {
+        config.mirror.organization_ids = vec![ORGANIZATION_ID.to_string()];
+    })
+    .await?;
+
+    let cursors = SyncCursors { pool: &app.pool };
+    for _ in 0..100 {
+        if cursors.get(ORGANIZATION_ID).await?.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    app.seed_token(false).await?;
+    Ok(app)
+}
+
+async fn sync(app: &App) -> Result<Value> {
+    let response = app
+        .client()
+        .post(format!("{}/sync/{ORGANIZATION_ID}", app.url()))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    Ok(response.json().await?)
+}
+
+async fn invoices(app: &App) -> Result<Vec<Value>> {
+    let body: Value = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={ORGANIZATION_ID}&date=2024-05-27",
+            app.url()
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    Ok(body["invoices"].as_array().unwrap().clone())
+}
+
+#[tokio::test]
+async fn synced_invoices_are_served_from_the_mirror() -> Result<()> {
+    let app = setup_synced_app().await?;
+
+    let report = sync(&app).await?;
+    assert_eq!(report["upserted"], 3);
+    assert_eq!(report["full"], true);
+
+    let requests = app.zoho.counters.invoice_requests.load(Ordering::SeqCst);
+    let ids: Vec<_> = invoices(&app)
+        .await?
+        .iter()
+        .map(|invoice| invoice["invoice_id"].as_str().unwrap().to_string())
+        .collect();
+
+    assert_eq!(ids, ["2", "1", "3"]);
+    assert_eq!(
+        app.zoho.counters.invoice_requests.load(Ordering::SeqCst),
+        requests
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn incremental_sync_picks_up_voided_and_deleted_invoices() -> Result<()> {
+    let app = setup_synced_app().await?;
+    sync(&app).await?;
+
+    app.zoho.void("2");
+    app.zoho.delete("3");
+
+    let report = sync(&app).await?;
+    assert_eq!(report["upserted"], 1);
+    assert_eq!(report["deleted"], 1);
+    assert_eq!(report["full"], false);
+
+    let invoices = invoices(&app).await?;
+    let ids: Vec<_> = invoices
+        .iter()
+        .map(|invoice| invoice["invoice_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["2", "1"]);
+    assert_eq!(invoices[0]["status"], "void");
+
+    let status: Value = app
+        .client()
+        .get(format!("{}/status/sync", app.url()))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(status[0]["organization_id"], ORGANIZATION_ID);
+    assert_eq!(status[0]["last_modified_time"], "2024-05-29T02:00:00Z");
+    assert_eq!(status[0]["last_error"], Value::Null);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unpaid_covers_every_open_status_in_the_mirror() -> Result<()> {
+    let app = setup_synced_app().await?;
+    app.zoho.void("2");
+    sync(&app).await?;
+
+    let body: Value = app
+        .client()
+        .get(format!(
+            "{}/invoices?organization_id={ORGANIZATION_ID}&date=2024-05-27&status=unpaid",
+            app.url()
+        ))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let ids: Vec<_> = body["invoices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|invoice| invoice["invoice_id"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(ids, ["1", "3"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn organizations_without_background_sync_are_read_from_zoho() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+    sync(&app).await?;
+
+    let requests = app.zoho.counters.invoice_requests.load(Ordering::SeqCst);
+    invoices(&app).await?;
+
+    assert!(app.zoho.counters.invoice_requests.load(Ordering::SeqCst) > requests);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stale_mirrors_are_read_from_zoho() -> Result<()> {
+    let app = setup_synced_app().await?;
+    sync(&app).await?;
+
+    sqlx::query("UPDATE sync_cursors SET last_synced_at = now() - interval '1 day'")
+        .execute(&app.pool)
+        .await?;
+
+    let requests = app.zoho.counters.invoice_requests.load(Ordering::SeqCst);
+    invoices(&app).await?;
+
+    assert!(app.zoho.counters.invoice_requests.load(Ordering::SeqCst) > requests);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_syncs_take_turns() -> Result<()> {
+    let app = setup_synced_app().await?;
+
+    let (first, second) = tokio::try_join!(sync(&app), sync(&app))?;
+
+    // the second one starts from where the first one left off
+    let full = [&first, &second]
+        .iter()
+        .filter(|report| report["full"] == true)
+        .count();
+    assert_eq!(full, 1);
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::Ordering;
+
+use crate::error::Result;
+use crate::helpers::setup_app;
+use crate::mock_zoho::ORGANIZATION_ID;
+
+#[tokio::test]
+async fn organizations_are_listed_from_zoho() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let organizations: serde_json::Value = app
+        .client()
+        .get(format!("{}/organizations", app.url()))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    assert_eq!(organizations[0]["organization_id"], ORGANIZATION_ID);
+    assert_eq!(organizations[0]["name"], "Delivr Sdn Bhd");
+    assert_eq!(organizations[0]["is_default_org"], true);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_organizations_are_listed_once_per_interval() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    for organization_id in ["1", "2", "3"] {
+        let response = app
+            .client()
+            .get(format!(
+                "{}/invoices?organization_id={organization_id}&date=2024-05-27",
+                app.url()
+            ))
+            .send()
+            .await?;
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    let requests = app
+        .zoho
+        .counters
+        .organization_requests
+        .load(Ordering::SeqCst);
+    assert_eq!(requests, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenants_do_not_share_tokens() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+    let (_, other) = app.create_tenant("other").await?;
+
+    let status: serde_json::Value = other
+        .get(format!("{}/status/tokens", app.url()))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(status, serde_json::json!([]));
+
+    // the organization belongs to the default tenant, whose token is not used
+    let response = other
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    let error: serde_json::Value = response.json().await?;
+    assert_eq!(error["code"], "zoho_not_connected");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_timezone_can_be_changed() -> Result<()> {
+    let app = setup_app().await?;
+
+    let tenant: serde_json::Value = app
+        .client()
+        .get(format!("{}/tenant", app.url()))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(tenant["name"], "default");
+    assert!(tenant["timezone"].is_null());
+
+    let response = app
+        .client()
+        .put(format!("{}/tenant", app.url()))
+        .json(&serde_json::json!({ "timezone": "Asia/Tokyo" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let tenant: serde_json::Value = response.json().await?;
+    assert_eq!(tenant["timezone"], "Asia/Tokyo");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tenant_settings_left_out_are_kept() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let url = format!("{}/tenant", app.url());
+    client
+        .put(&url)
+        .json(&serde_json::json!({ "timezone": "Asia/Tokyo" }))
+        .send()
+        .await?;
+
+    let tenant: serde_json::Value = client
+        .put(&url)
+        .json(&serde_json::json!({ "default_organization_id": ORGANIZATION_ID }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(tenant["timezone"], "Asia/Tokyo");
+    assert_eq!(tenant["default_organization_id"], ORGANIZATION_ID);
+
+    // an explicit null clears the setting
+    let tenant: serde_json::Value = client
+        .put(&url)
+        .json(&serde_json::json!({ "timezone": null }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(tenant["timezone"].is_null());
+    assert_eq!(tenant["default_organization_id"], ORGANIZATION_ID);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invoices_default_to_the_discovered_organization() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .get(format!("{}/invoices?date=2024-05-27", app.url()))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["invoices"].as_array().map(Vec::len), Some(3));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn default_organization_must_belong_to_the_tenant() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let response = client
+        .put(format!("{}/tenant", app.url()))
+        .json(&serde_json::json!({ "default_organization_id": "0" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let response = client
+        .put(format!("{}/tenant", app.url()))
+        .json(&serde_json::json!({ "default_organization_id": ORGANIZATION_ID }))
+        .send()
+        .await?;
+    let tenant: serde_json::Value = response.json().await?;
+    assert_eq!(tenant["default_organization_id"], ORGANIZATION_ID);
+
+    Ok(())
+}
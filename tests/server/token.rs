@@ -0,0 +1,385 @@
+use std::sync::atomic::Ordering;
+
+use delivr::auth::Role;
+use delivr::database::Tokens;
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::redirect::Policy;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::Row;
+
+use crate::error::Result;
+use crate::helpers::{setup_app, setup_app_with, App};
+use crate::mock_zoho::{GRANT_CODE, ORGANIZATION_ID, SCOPE};
+
+/// Starts the OAuth flow and returns the signed `state` and the cookie that binds it to the browser.
+async fn authorize(app: &App, client: &reqwest::Client) -> Result<(String, String)> {
+    let response = client
+        .get(format!("{}/oauth/authorize", app.url()))
+        .send()
+        .await?;
+
+    assert!(response.status().is_redirection());
+
+    let location = reqwest::Url::parse(response.headers()[LOCATION].to_str()?)?;
+    assert!(location.as_str().starts_with(&app.zoho.url));
+
+    let query: std::collections::HashMap<_, _> = location.query_pairs().into_owned().collect();
+    assert_eq!(query["access_type"], "offline");
+    assert_eq!(query["scope"], SCOPE);
+
+    let cookie = response.headers()[SET_COOKIE].to_str()?;
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    Ok((query["state"].clone(), cookie))
+}
+
+async fn callback(
+    app: &App,
+    client: &reqwest::Client,
+    query: &[(&str, &str)],
+    cookie: &str,
+) -> Result<String> {
+    let response = client
+        .get(format!("{}/oauth/callback", app.url()))
+        .query(query)
+        .header(COOKIE, cookie)
+        .send()
+        .await?;
+
+    assert!(response.status().is_redirection());
+    Ok(response.headers()[LOCATION].to_str()?.to_string())
+}
+
+#[tokio::test]
+async fn oauth_flow_stores_token() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let (state, cookie) = authorize(&app, &client).await?;
+    let location = callback(
+        &app,
+        &client,
+        &[("code", GRANT_CODE), ("state", &state)],
+        &cookie,
+    )
+    .await?;
+
+    assert_eq!(location, "/?oauth=success");
+
+    let token: serde_json::Value = client
+        .get(format!("{}/tokens/{}", app.url(), SCOPE))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    assert_eq!(token["scope"], SCOPE);
+    assert_eq!(token["api_domain"], app.zoho.url);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reconnecting_clears_a_revoked_grant() -> Result<()> {
+    let app = setup_app_with(|config| config.zoho.refresh_interval_secs = 1).await?;
+    app.seed_revoked_token().await?;
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let response = client
+        .get(format!(
+            "{}/invoices?organization_id={}&date=2024-05-27",
+            app.url(),
+            ORGANIZATION_ID
+        ))
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    let (state, cookie) = authorize(&app, &client).await?;
+    let location = callback(
+        &app,
+        &client,
+        &[("code", GRANT_CODE), ("state", &state)],
+        &cookie,
+    )
+    .await?;
+    assert_eq!(location, "/?oauth=success");
+
+    let status: serde_json::Value = client
+        .get(format!("{}/status/tokens", app.url()))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(status[0]["status"], "valid");
+    assert!(status[0]["last_refresh_error"].is_null());
+
+    // once the new token is about to expire, the background refresher renews it
+    sqlx::query("UPDATE tokens SET time_stamp = now() - interval '2 hours'")
+        .execute(&app.pool)
+        .await?;
+    for _ in 0..100 {
+        if app.zoho.counters.refreshes() > 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(app.zoho.counters.refreshes(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oauth_flow_succeeds_when_organizations_cannot_be_listed() -> Result<()> {
+    let app = setup_app_with(|config| config.zoho.retry.max_retries = 0).await?;
+    app.zoho.take_organizations_down();
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let (state, cookie) = authorize(&app, &client).await?;
+    let location = callback(
+        &app,
+        &client,
+        &[("code", GRANT_CODE), ("state", &state)],
+        &cookie,
+    )
+    .await?;
+
+    assert_eq!(location, "/?oauth=success");
+    assert_eq!(
+        app.zoho
+            .counters
+            .organization_requests
+            .load(Ordering::SeqCst),
+        1
+    );
+
+    let token: serde_json::Value = client
+        .get(format!("{}/tokens/{}", app.url(), SCOPE))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(token["scope"], SCOPE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oauth_callback_rejects_forged_state() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let (state, cookie) = authorize(&app, &client).await?;
+    // change the last hex digit of the signature, whatever it is
+    let last = if state.ends_with('0') { '1' } else { '0' };
+    let forged = format!("{}{last}", &state[..state.len() - 1]);
+
+    let location = callback(
+        &app,
+        &client,
+        &[("code", GRANT_CODE), ("state", &forged)],
+        &cookie,
+    )
+    .await?;
+
+    assert_eq!(location, "/?oauth=error");
+    assert_eq!(
+        app.zoho
+            .counters
+            .token_exchanges
+            .load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oauth_callback_rejects_state_from_another_browser() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let (state, _) = authorize(&app, &client).await?;
+    let (_, other_cookie) = authorize(&app, &client).await?;
+
+    let location = callback(
+        &app,
+        &client,
+        &[("code", GRANT_CODE), ("state", &state)],
+        &other_cookie,
+    )
+    .await?;
+
+    assert_eq!(location, "/?oauth=error");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oauth_callback_with_invalid_code_fails() -> Result<()> {
+    let app = setup_app().await?;
+    let client = app.client_builder().redirect(Policy::none()).build()?;
+
+    let (state, cookie) = authorize(&app, &client).await?;
+    let location = callback(
+        &app,
+        &client,
+        &[("code", "not-a-code"), ("state", &state)],
+        &cookie,
+    )
+    .await?;
+
+    assert_eq!(location, "/?oauth=error");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn token_endpoints_do_not_expose_secrets() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let client = app.client();
+    let token: serde_json::Value = client
+        .get(format!("{}/tokens/{}", app.url(), SCOPE))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    assert_eq!(token["scope"], SCOPE);
+    assert!(token.get("access_token").is_none());
+    assert!(token.get("refresh_token").is_none());
+
+    let body = client
+        .get(format!("{}/tokens", app.url()))
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    assert!(!body.contains("access-seeded"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn token_export_requires_admin() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let url = format!("{}/admin/tokens/export", app.url());
+
+    let response = reqwest::Client::new().get(&url).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth("wrong")
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let manager = app.client_as(Role::Manager).await?;
+    let response = manager.get(&url).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let exported: serde_json::Value = app.client().get(&url).send().await?.json().await?;
+
+    assert_eq!(exported[0]["access_token"], "access-seeded");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tokens_are_encrypted_at_rest() -> Result<()> {
+    let app = setup_app().await?;
+    app.seed_token(false).await?;
+
+    let row = sqlx::query("SELECT access_token, access_token_sealed, key_id FROM tokens")
+        .fetch_one(&app.pool)
+        .await?;
+
+    let plaintext: Option<String> = row.try_get("access_token")?;
+    let sealed: Vec<u8> = row.try_get("access_token_sealed")?;
+    let key_id: String = row.try_get("key_id")?;
+
+    assert!(plaintext.is_none());
+    assert!(!String::from_utf8_lossy(&sealed).contains("access-seeded"));
+    assert_eq!(key_id, app.cipher.active_key_id());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn plaintext_tokens_are_reencrypted() -> Result<()> {
+    let app = setup_app().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO tokens (access_token, api_domain, expires_in, refresh_token, scope, token_type, time_stamp, tenant_id)
+        VALUES ('legacy-access', $1, 3600, 'legacy-refresh', $2, 'Bearer', now(), $3)
+        "#,
+    )
+    .bind(&app.zoho.url)
+    .bind(SCOPE)
+    .bind(app.tenant_id)
+    .execute(&app.pool)
+    .await?;
+
+    let tokens = Tokens {
+        pool: &app.pool,
+        cipher: &app.cipher,
+    };
+
+    assert_eq!(tokens.reencrypt_all().await?, 1);
+    assert_eq!(tokens.reencrypt_all().await?, 0);
+
+    let token = tokens.get_by_scope(app.tenant_id, SCOPE).await?.unwrap();
+    assert_eq!(token.access_token.expose_secret(), "legacy-access");
+
+    let plaintext: Option<String> = sqlx::query_scalar("SELECT access_token FROM tokens")
+        .fetch_one(&app.pool)
+        .await?;
+    assert!(plaintext.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tokens_sealed_without_tenant_are_resealed() -> Result<()> {
+    let app = setup_app().await?;
+
+    // sealed the way rows were before tenants, bound to the scope only
+    let data_key = app.cipher.new_data_key()?;
+    let access_token = data_key.seal(&Secret::new("legacy-access".to_string()), SCOPE)?;
+    sqlx::query(
+        r#"
+        INSERT INTO tokens (access_token_sealed, api_domain, expires_in, scope, token_type, time_stamp, key_id, wrapped_key, tenant_id)
+        VALUES ($1, $2, 3600, $3, 'Bearer', now(), $4, $5, $6)
+        "#,
+    )
+    .bind(access_token)
+    .bind(&app.zoho.url)
+    .bind(SCOPE)
+    .bind(&data_key.key_id)
+    .bind(&data_key.wrapped)
+    .bind(app.tenant_id)
+    .execute(&app.pool)
+    .await?;
+
+    let tokens = Tokens {
+        pool: &app.pool,
+        cipher: &app.cipher,
+    };
+    let token = tokens.get_by_scope(app.tenant_id, SCOPE).await?.unwrap();
+    assert_eq!(token.access_token.expose_secret(), "legacy-access");
+
+    assert_eq!(tokens.reencrypt_all().await?, 1);
+    assert_eq!(tokens.reencrypt_all().await?, 0);
+
+    let token = tokens.get_by_scope(app.tenant_id, SCOPE).await?.unwrap();
+    assert_eq!(token.access_token.expose_secret(), "legacy-access");
+
+    Ok(())
+}